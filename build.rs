@@ -0,0 +1,73 @@
+//! Records the resolved versions of the direct dependencies and the rustc
+//! version so that machine-readable reports can say exactly what was measured.
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+/// Names listed under `[dependencies]` in Cargo.toml.
+fn direct_dependencies(manifest: &str) -> Vec<String> {
+    let mut in_dependencies = false;
+    let mut names = Vec::new();
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_dependencies = line == "[dependencies]";
+        } else if in_dependencies {
+            if let Some((name, _)) = line.split_once('=') {
+                names.push(name.trim().to_string());
+            }
+        }
+    }
+    names
+}
+
+/// `(name, version)` for every `[[package]]` in Cargo.lock.
+fn locked_packages(lock: &str) -> Vec<(String, String)> {
+    let mut packages = Vec::new();
+    let mut name = None;
+    for line in lock.lines().map(str::trim) {
+        if line == "[[package]]" {
+            name = None;
+        } else if let Some(value) = line.strip_prefix("name = ") {
+            name = Some(value.trim_matches('"').to_string());
+        } else if let Some(value) = line.strip_prefix("version = ") {
+            if let Some(name) = name.take() {
+                packages.push((name, value.trim_matches('"').to_string()));
+            }
+        }
+    }
+    packages
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let manifest = fs::read_to_string("Cargo.toml").unwrap_or_default();
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let packages = locked_packages(&lock);
+    let versions: Vec<String> = direct_dependencies(&manifest)
+        .iter()
+        .map(|dep| {
+            let version = packages
+                .iter()
+                .find(|(name, _)| name == dep)
+                .map_or("unknown", |(_, version)| version.as_str());
+            format!("{}={}", dep, version)
+        })
+        .collect();
+    println!(
+        "cargo:rustc-env=BENCH_CRATE_VERSIONS={}",
+        versions.join(",")
+    );
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=BENCH_RUSTC_VERSION={}", rustc_version);
+}
@@ -0,0 +1,256 @@
+//! Saved reports to compare later runs against, e.g. across a toolchain or
+//! dependency bump.
+//!
+//! A baseline is the full JSON report. `--compare` matches its codecs to the
+//! current run's by label and thread count, and calls a throughput change
+//! significant when the 95% bootstrap confidence intervals of the two means
+//! do not overlap.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::harness::BenchmarkResults;
+use crate::report::{self, Measurement, Report};
+use crate::stats;
+
+/// Directory baselines given by name are kept in.
+const BASELINE_DIR: &str = "baselines";
+
+/// Where the baseline `name` lives: `name` itself if it looks like a path to
+/// a JSON file, otherwise `baselines/<name>.json`.
+pub fn path(name: &str) -> PathBuf {
+    if name.ends_with(".json") || name.contains('/') {
+        PathBuf::from(name)
+    } else {
+        PathBuf::from(BASELINE_DIR).join(format!("{}.json", name))
+    }
+}
+
+/// Writes `report` as the baseline `name`, creating its directory if needed.
+pub fn save(name: &str, report: &Report) -> io::Result<PathBuf> {
+    let path = path(name);
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = fs::File::create(&path)?;
+    report::write_json(&mut file, report)?;
+    Ok(path)
+}
+
+/// Reads the baseline `name`.
+pub fn load(name: &str) -> io::Result<Report> {
+    let path = path(name);
+    let json = fs::read_to_string(&path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    serde_json::from_str(&json).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+/// One direction's throughput in the baseline and now.
+#[derive(Serialize, Deserialize)]
+pub struct ThroughputDelta {
+    /// Aggregate MiB/s in the baseline and in this run.
+    pub baseline: f64,
+    pub current: f64,
+    /// Relative change, negative when this run is slower.
+    pub change: f64,
+    /// Whether the confidence intervals of the mean per-run throughput are
+    /// disjoint; `None` when either side has fewer than two runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub significant: Option<bool>,
+}
+
+impl ThroughputDelta {
+    fn new(baseline: (f64, Vec<f64>), current: (f64, Vec<f64>)) -> Self {
+        let significant = match (
+            stats::summarize(&baseline.1).filter(|_| baseline.1.len() > 1),
+            stats::summarize(&current.1).filter(|_| current.1.len() > 1),
+        ) {
+            (Some(before), Some(after)) => {
+                Some(after.ci_high < before.ci_low || after.ci_low > before.ci_high)
+            }
+            _ => None,
+        };
+        Self {
+            baseline: baseline.0,
+            current: current.0,
+            change: current.0 / baseline.0 - 1.0,
+            significant,
+        }
+    }
+
+    /// Slower by more than `threshold`, and the runs show it is not noise.
+    fn regressed(&self, threshold: f64) -> bool {
+        self.change < -threshold && self.significant == Some(true)
+    }
+
+    /// Slower by more than `threshold`, with too few runs to tell whether
+    /// that is noise.
+    fn unconfirmed(&self, threshold: f64) -> bool {
+        self.change < -threshold && self.significant.is_none()
+    }
+}
+
+/// A codec measured both in the baseline and now.
+#[derive(Serialize, Deserialize)]
+pub struct CodecDelta {
+    pub algorithm: String,
+    pub threads: usize,
+    pub baseline_ratio: f64,
+    pub ratio: f64,
+    /// `None` when the codec failed in either run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress: Option<ThroughputDelta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decompress: Option<ThroughputDelta>,
+    /// Why this codec counts as a regression, if it does.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regression: Option<String>,
+    /// Slowdowns beyond the threshold that too few runs could not confirm;
+    /// reported, but not a regression.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unconfirmed: Option<String>,
+}
+
+impl CodecDelta {
+    pub fn label(&self) -> String {
+        Measurement::label_for(&self.algorithm, self.threads)
+    }
+}
+
+/// The current run against a saved baseline.
+#[derive(Serialize, Deserialize)]
+pub struct Comparison {
+    pub baseline: String,
+    /// Relative slowdown or ratio loss that counts as a regression.
+    pub threshold: f64,
+    /// The baseline's input and chunk size, when they differ from this run's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline_input: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline_chunk_size: Option<usize>,
+    pub codecs: Vec<CodecDelta>,
+    /// Codecs only in this run, and only in the baseline.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
+}
+
+impl Comparison {
+    pub fn regressions(&self) -> impl Iterator<Item = &CodecDelta> {
+        self.codecs
+            .iter()
+            .filter(|codec| codec.regression.is_some())
+    }
+
+    pub fn unconfirmed(&self) -> impl Iterator<Item = &CodecDelta> {
+        self.codecs
+            .iter()
+            .filter(|codec| codec.unconfirmed.is_some())
+    }
+}
+
+fn throughputs(results: &BenchmarkResults) -> ((f64, Vec<f64>), (f64, Vec<f64>)) {
+    let (compress, decompress) = report::aggregate_throughput(results);
+    (
+        (compress, results.compress_throughputs()),
+        (decompress, results.decompress_throughputs()),
+    )
+}
+
+/// Compares `current` with `baseline`, flagging every codec that failed
+/// only now, lost more than `threshold` of its ratio, or got significantly
+/// slower by more than `threshold` in either direction. Slowdowns that too
+/// few runs leave unconfirmed are flagged separately.
+pub fn compare(name: &str, baseline: &Report, current: &Report, threshold: f64) -> Comparison {
+    let find = |report: &'_ Report, measurement: &Measurement| {
+        report
+            .results
+            .iter()
+            .position(|m| m.algorithm == measurement.algorithm && m.threads == measurement.threads)
+    };
+
+    let mut codecs = Vec::new();
+    let mut added = Vec::new();
+    for measurement in &current.results {
+        let Some(index) = find(baseline, measurement) else {
+            added.push(measurement.label());
+            continue;
+        };
+        let (before, after) = (&baseline.results[index].results, &measurement.results);
+        let (baseline_ratio, ratio) = (report::ratio(before), report::ratio(after));
+        let mut delta = CodecDelta {
+            algorithm: measurement.algorithm.clone(),
+            threads: measurement.threads,
+            baseline_ratio,
+            ratio,
+            compress: None,
+            decompress: None,
+            regression: None,
+            unconfirmed: None,
+        };
+        if let Some(failure) = &after.failure {
+            if before.failure.is_none() {
+                delta.regression = Some(format!("failed: {}", failure));
+            }
+            codecs.push(delta);
+            continue;
+        }
+        if before.failure.is_some() || before.input_sizes.is_empty() {
+            codecs.push(delta);
+            continue;
+        }
+
+        let (compress_before, decompress_before) = throughputs(before);
+        let (compress_after, decompress_after) = throughputs(after);
+        let compress = ThroughputDelta::new(compress_before, compress_after);
+        let decompress = ThroughputDelta::new(decompress_before, decompress_after);
+        let mut reasons = Vec::new();
+        let mut unconfirmed = Vec::new();
+        if ratio / baseline_ratio - 1.0 < -threshold {
+            reasons.push(format!("ratio {:.2} -> {:.2}", baseline_ratio, ratio));
+        }
+        for (direction, throughput) in [("compress", &compress), ("decompress", &decompress)] {
+            let change = format!("{} {:+.1}%", direction, throughput.change * 100.0);
+            if throughput.regressed(threshold) {
+                reasons.push(change);
+            } else if throughput.unconfirmed(threshold) {
+                unconfirmed.push(change);
+            }
+        }
+        if !reasons.is_empty() {
+            delta.regression = Some(reasons.join(", "));
+        }
+        if !unconfirmed.is_empty() {
+            delta.unconfirmed = Some(unconfirmed.join(", "));
+        }
+        delta.compress = Some(compress);
+        delta.decompress = Some(decompress);
+        codecs.push(delta);
+    }
+    let removed = baseline
+        .results
+        .iter()
+        .filter(|measurement| find(current, measurement).is_none())
+        .map(Measurement::label)
+        .collect();
+
+    Comparison {
+        baseline: name.to_string(),
+        threshold,
+        baseline_input: (baseline.input != current.input).then(|| baseline.input.clone()),
+        baseline_chunk_size: (baseline.chunk_size != current.chunk_size)
+            .then_some(baseline.chunk_size),
+        codecs,
+        added,
+        removed,
+    }
+}
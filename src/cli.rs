@@ -0,0 +1,726 @@
+//! Command-line parsing.
+
+use std::collections::BTreeMap;
+use std::process;
+use std::time::Duration;
+
+use crate::codecs::{Mode, ZstdParams, ZSTD_STRATEGIES};
+use crate::cost::CostModel;
+use crate::harness::RunBudget;
+use crate::pareto::Requirement;
+use crate::report::Format;
+use crate::stats::Stat;
+use crate::synthetic::{self, Spec};
+
+pub struct Options {
+    /// Files and directories to benchmark, each file on its own and all of
+    /// them as one corpus.
+    pub paths: Vec<String>,
+    /// Generated data to benchmark instead of a file.
+    pub synthetic: Option<Spec>,
+    /// Seed of the `--synthetic` generator.
+    pub seed: u64,
+    /// Pre-compressed files to time decoders on, for the `decompress`
+    /// subcommand; `None` for the normal benchmark.
+    pub decompress: Option<Vec<String>>,
+    pub num_runs: usize,
+    /// Adaptive run count from `--min-time`/`--max-time`, if either was given.
+    pub budget: Option<RunBudget>,
+    pub algorithms: Vec<String>,
+    pub exclude: Vec<String>,
+    pub list_algorithms: bool,
+    /// Compression levels to sweep, keyed by codec family.
+    pub levels: BTreeMap<String, Vec<i32>>,
+    pub format: Format,
+    /// Where to write the report instead of stdout.
+    pub output: Option<String>,
+    /// Throughput statistic shown in the results table.
+    pub stat: Stat,
+    pub verify_each_run: bool,
+    /// Cross-decode every implementation's output instead of benchmarking.
+    pub interop: bool,
+    /// Size of the independently compressed units, in bytes.
+    pub chunk_size: usize,
+    /// Block size for dropping all-zero blocks before benchmarking.
+    pub skip_zero_blocks: Option<usize>,
+    /// Thread counts to run every codec at.
+    pub threads: Vec<usize>,
+    /// Block API container formats to run, keyed by codec family or `all`.
+    pub containers: BTreeMap<String, Vec<String>>,
+    /// Which library APIs to benchmark.
+    pub modes: Vec<Mode>,
+    /// Size of each read or write made by stream variants.
+    pub io_buffer: usize,
+    /// Size of the zstd dictionary to train on the input, if any.
+    pub zstd_dict: Option<usize>,
+    /// Fraction of the input's chunks the dictionary is trained on.
+    pub dict_sample: f64,
+    /// Streaming zstd variants to run alongside the one-shot levels.
+    pub zstd_streams: Vec<ZstdParams>,
+    /// CPU to pin the benchmark and its worker threads to.
+    pub pin_cpu: Option<usize>,
+    /// How long to busy-loop before the first measurement, in milliseconds.
+    pub spin_ms: u64,
+    /// Relative memcpy bandwidth drift between start and end that is warned
+    /// about.
+    pub drift_threshold: f64,
+    /// Baseline to save this run's report as.
+    pub save_baseline: Option<String>,
+    /// Baseline to compare this run against.
+    pub compare: Option<String>,
+    /// Relative slowdown or ratio loss against the baseline that fails the run.
+    pub regression_threshold: f64,
+    /// Constraints the recommended algorithm has to meet.
+    pub require: Vec<Requirement>,
+    /// Prices and workload to rank algorithms by total cost.
+    pub cost_model: Option<CostModel>,
+}
+
+const DEFAULT_CHUNK_SIZE: usize = 256 * 1024 * 1024;
+const DEFAULT_IO_BUFFER: usize = 64 * 1024;
+const DEFAULT_DICT_SAMPLE: f64 = 0.1;
+const DEFAULT_SPIN_MS: u64 = 500;
+const DEFAULT_MAX_TIME: Duration = Duration::from_secs(10);
+const DEFAULT_TARGET_STDERR: f64 = 0.01;
+const DEFAULT_DRIFT_THRESHOLD: f64 = 0.05;
+const DEFAULT_REGRESSION_THRESHOLD: f64 = 0.05;
+
+const PATHS_HELP: &str = "\
+Each path is a file or a directory, walked recursively. Several files are
+benchmarked as one corpus, with a table per file after the corpus table.";
+
+const OPTIONS_HELP: &str = "\
+Options:
+  --synthetic <KIND:SIZE> Benchmark SIZE bytes of generated data instead of a
+                          file: zeros, random (incompressible), pattern[=PERIOD]
+                          (a random PERIOD-byte pattern repeated, default 4K),
+                          text (Markov-chain words), entropy[=BITS] (skewed
+                          bytes with BITS of entropy per byte, default 4) or
+                          mixed[=FRACTION] (64K regions of text and random
+                          bytes, FRACTION of them text, default 0.5),
+                          e.g. entropy=2.5:256M
+  --seed <N>              Seed for --synthetic (default: 24301)
+  --runs <N>              Number of runs per algorithm (default: 1); the minimum
+                          with --min-time or --max-time
+  --min-time <TIME>       Keep running each algorithm on each batch (up to
+                          256 MiB of a file) until it has spent TIME there
+                          (e.g. 2s or 500ms) and its throughput is stable
+  --max-time <TIME>       Stop running an algorithm on a batch once it has
+                          spent TIME there, stable or not (default with
+                          --min-time: 10s)
+  --target-stderr <PERCENT>
+                          Relative standard error of mean throughput at which
+                          --min-time considers it stable (default: 1)
+  --algorithms <LIST>     Comma-separated names or glob patterns to run (e.g. 'zstd*,lz4*')
+  --exclude <LIST>        Comma-separated names or glob patterns to skip
+  --list-algorithms       Print the available algorithms and exit
+  --levels <LIST>         Compression levels to sweep per codec family, as
+                          comma-separated FAMILY=N or FAMILY=A..B entries
+                          (e.g. 'zstd=-5..19,libdeflate=1..12,xz2=0..9')
+  --format <FORMAT>       Report format: markdown (default), json or csv
+  --output <PATH>         Write the report to PATH instead of stdout
+  --stat <STAT>           Throughput shown in the results table: total (default,
+                          total bytes / total time), min, max, mean, median,
+                          p95 or p99 (slow tail: 95%/99% of runs were faster)
+  --chunk-size <SIZE>     Compress the input in independent chunks of SIZE bytes,
+                          with optional K, M or G suffix (default: 256M)
+  --skip-zero-blocks <SIZE>
+                          Drop all-zero blocks of SIZE bytes before benchmarking
+                          and report an effective ratio counting them as free
+  --threads <LIST>        Compress chunks on a pool of N worker threads; a list
+                          such as '1,2,4,8,all' sweeps thread counts (default: 1)
+  --containers <LIST>     Container formats to run as comma-separated FAMILY=NAME
+                          entries, FAMILY=all, or all for every family:
+                          flate2, miniz_oxide and libdeflate: gzip, zlib, raw;
+                          lz4 and lz4_flex: block, frame, frame-checksum;
+                          snap: raw, framed (CRC32C per chunk);
+                          xz2: xz, lzma (legacy .lzma). lzma-rs runs lzma only,
+                          as its xz writer stores data uncompressed
+  --mode <LIST>           Library APIs to benchmark: block (one-shot calls over
+                          whole chunks, default), stream (io::Write encoders and
+                          io::Read decoders) or block,stream for both
+  --io-buffer <SIZE>      Size of each write and read made by stream variants
+                          (default: 64K)
+  --zstd-dict <SIZE>      Train a zstd dictionary of up to SIZE bytes (e.g. 112K)
+                          on a sample of the input's chunks and add a row using
+                          it for every zstd level
+  --dict-sample <FRACTION>
+                          Fraction of chunks the dictionary is trained on, spread
+                          evenly across the input (default: 0.1)
+  --zstd-stream <PARAMS>  Also run zstd's streaming API with comma-separated
+                          parameters: level=N, workers=N (internal threads),
+                          ldm (long-distance matching), wlog=N (window log)
+                          and strategy=NAME (fast .. btultra2); repeatable
+  --interop               Instead of the normal benchmark, compress with every
+                          implementation of each shared format (DEFLATE, zlib,
+                          gzip, LZ4 block and frame, xz, lzma) and decompress
+                          with every other, timing each pair and flagging
+                          incompatible ones
+  --verify-each-run       Verify the round trip after every timed run, not only
+                          after the warm-up
+  --pin-cpu <N>           Pin the benchmark, worker threads included, to CPU N
+  --spin <MS>             Busy-loop for MS milliseconds before the first
+                          measurement so the CPU clock settles (default: 500)
+  --drift-threshold <PERCENT>
+                          Warn when memcpy bandwidth measured after the run
+                          differs from before it by more than PERCENT
+                          (default: 5)
+  --save-baseline <NAME>  Save the report as baseline NAME, in
+                          baselines/NAME.json (or at NAME if it is a .json path)
+  --compare <NAME>        Compare ratio and throughput with baseline NAME and exit
+                          with status 1 if any algorithm regressed
+  --regression-threshold <PERCENT>
+                          Slowdown or ratio loss that --compare counts as a
+                          regression (default: 5)
+  --require <LIST>        Recommend the highest-ratio algorithm meeting every
+                          comma-separated constraint, e.g. compress>=500MiB/s,
+                          decompress>=1GiB/s or ratio>=3; repeatable
+  --cost-model <PARAMS>   Rank algorithms by projected cost per TiB stored, from
+                          comma-separated storage=$/GiB-month, cpu=$/core-hour,
+                          reads=N (decompressions per stripe) and
+                          retention=MONTHS";
+
+const DECOMPRESS_HELP: &str = "\
+The decompress subcommand times every decoder of each file's format, detected
+from its magic bytes: zstd, xz, LZ4 frame, framed Snappy, gzip, legacy lzma
+and zlib. It accepts --runs, --algorithms, --exclude, --list-algorithms,
+--format, --output, --stat, --io-buffer and --verify-each-run.";
+
+fn usage(program: &str) -> String {
+    format!(
+        "Usage: {0} [OPTIONS] <path>...\n       {0} [OPTIONS] --synthetic <KIND:SIZE>\n       {0} decompress [OPTIONS] <file>...\n\n{1}\n\n{2}\n\n{3}",
+        program, PATHS_HELP, OPTIONS_HELP, DECOMPRESS_HELP
+    )
+}
+
+/// Prints `message` and the usage text, then exits with status 1.
+fn fail(program: &str, message: &str) -> ! {
+    eprintln!("Error: {}", message);
+    eprintln!("{}", usage(program));
+    process::exit(1);
+}
+
+/// Returns the value following the option at `index`.
+fn value(args: &[String], index: usize) -> &str {
+    match args.get(index + 1) {
+        Some(value) => value,
+        None => fail(&args[0], &format!("{} requires a value", args[index])),
+    }
+}
+
+/// Splits a comma-separated list, dropping empty entries.
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses a byte size such as `4096`, `4K`, `64KiB`, `1M` or `2G`. Suffixes are
+/// binary (1K = 1024 bytes).
+pub fn parse_size(s: &str) -> Option<usize> {
+    let s = s.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, suffix) = s.split_at(digits);
+    let number: usize = number.parse().ok()?;
+    let multiplier: usize = match suffix.to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
+
+/// Parses a duration such as `2`, `2s`, `1.5s` or `500ms`; bare numbers are
+/// seconds.
+fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let (number, scale) = match s.strip_suffix("ms") {
+        Some(number) => (number, 1e-3),
+        None => (s.strip_suffix('s').unwrap_or(s), 1.0),
+    };
+    let seconds = number.trim().parse::<f64>().ok()? * scale;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Parses a `--threads` list such as `4` or `1,2,4,8,all`, where `all` is
+/// the number of CPUs available to the process.
+fn parse_threads(list: &str) -> Result<Vec<usize>, String> {
+    let mut threads = Vec::new();
+    for entry in split_list(list) {
+        let count = match entry.as_str() {
+            "all" => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n.parse().ok().filter(|&n| n > 0).ok_or_else(|| {
+                format!("--threads entry '{}' must be a positive number or 'all'", n)
+            })?,
+        };
+        if !threads.contains(&count) {
+            threads.push(count);
+        }
+    }
+    if threads.is_empty() {
+        return Err("--threads requires at least one thread count".to_string());
+    }
+    Ok(threads)
+}
+
+/// Parses `--levels` entries such as `zstd=1..19` or `xz2=6` into `levels`.
+/// Ranges are inclusive and may be negative (`zstd=-5..-1`).
+fn parse_levels(list: &str, levels: &mut BTreeMap<String, Vec<i32>>) -> Result<(), String> {
+    for entry in split_list(list) {
+        let (family, range) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("--levels entry '{}' must look like FAMILY=A..B", entry))?;
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<i32>()
+                .map_err(|_| format!("--levels entry '{}' has an invalid level '{}'", entry, s))
+        };
+        let (first, last) = match range.split_once("..") {
+            Some((first, last)) => (parse(first)?, parse(last)?),
+            None => (parse(range)?, parse(range)?),
+        };
+        if first > last {
+            return Err(format!("--levels entry '{}' has an empty range", entry));
+        }
+        let requested = levels.entry(family.trim().to_string()).or_default();
+        for level in first..=last {
+            if !requested.contains(&level) {
+                requested.push(level);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses `--containers` entries such as `flate2=zlib`, `lz4=all` or `all`
+/// into `containers`. Family and container names are checked by the registry.
+fn parse_containers(
+    list: &str,
+    containers: &mut BTreeMap<String, Vec<String>>,
+) -> Result<(), String> {
+    for entry in split_list(list) {
+        let (family, container) = match entry.split_once('=') {
+            Some((family, container)) => (family.trim(), container.trim()),
+            None if entry == "all" => ("all", "all"),
+            None => {
+                return Err(format!(
+                    "--containers entry '{}' must look like FAMILY=NAME or all",
+                    entry
+                ))
+            }
+        };
+        let requested = containers.entry(family.to_string()).or_default();
+        if !requested.iter().any(|c| c == container) {
+            requested.push(container.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Parses `--zstd-stream` parameters such as `level=19,workers=4,ldm,wlog=27`.
+fn parse_zstd_stream(list: &str) -> Result<ZstdParams, String> {
+    let mut params = ZstdParams::default();
+    for entry in split_list(list) {
+        let (key, value) = match entry.split_once('=') {
+            Some((key, value)) => (key.trim(), Some(value.trim())),
+            None => (entry.as_str(), None),
+        };
+        let number = |range: std::ops::RangeInclusive<i64>| {
+            value
+                .and_then(|v| v.parse::<i64>().ok())
+                .filter(|n| range.contains(n))
+                .ok_or_else(|| {
+                    format!(
+                        "--zstd-stream {} must be a number in {}..{}",
+                        key,
+                        range.start(),
+                        range.end()
+                    )
+                })
+        };
+        match key {
+            "level" => {
+                let levels = ::zstd::zstd_safe::min_c_level()..=::zstd::zstd_safe::max_c_level();
+                params.level = Some(number(*levels.start() as i64..=*levels.end() as i64)? as i32);
+            }
+            "workers" => params.workers = number(0..=200)? as u32,
+            "wlog" => params.window_log = Some(number(10..=31)? as u32),
+            "ldm" => params.long_distance_matching = true,
+            "strategy" => {
+                let name = value.unwrap_or_default();
+                params.strategy = Some(
+                    ZSTD_STRATEGIES
+                        .iter()
+                        .position(|&(s, _)| s == name)
+                        .ok_or_else(|| format!("--zstd-stream: unknown strategy '{}'", name))?,
+                );
+            }
+            _ => return Err(format!("--zstd-stream: unknown parameter '{}'", key)),
+        }
+    }
+    Ok(params)
+}
+
+pub fn parse_args(args: &[String]) -> Options {
+    let program = &args[0];
+    let mut options = Options {
+        paths: Vec::new(),
+        synthetic: None,
+        seed: synthetic::DEFAULT_SEED,
+        decompress: None,
+        num_runs: 1, // Default to 1 run
+        budget: None,
+        algorithms: Vec::new(),
+        exclude: Vec::new(),
+        list_algorithms: false,
+        levels: BTreeMap::new(),
+        format: Format::Markdown,
+        output: None,
+        stat: Stat::Total,
+        verify_each_run: false,
+        interop: false,
+        chunk_size: DEFAULT_CHUNK_SIZE,
+        skip_zero_blocks: None,
+        threads: vec![1],
+        containers: BTreeMap::new(),
+        modes: vec![Mode::Block],
+        io_buffer: DEFAULT_IO_BUFFER,
+        zstd_dict: None,
+        dict_sample: DEFAULT_DICT_SAMPLE,
+        zstd_streams: Vec::new(),
+        pin_cpu: None,
+        spin_ms: DEFAULT_SPIN_MS,
+        drift_threshold: DEFAULT_DRIFT_THRESHOLD,
+        save_baseline: None,
+        compare: None,
+        regression_threshold: DEFAULT_REGRESSION_THRESHOLD,
+        require: Vec::new(),
+        cost_model: None,
+    };
+
+    let mut min_time = None;
+    let mut max_time = None;
+    let mut target_stderr = DEFAULT_TARGET_STDERR;
+    let mut arg_index = 1;
+    if args.get(1).map(String::as_str) == Some("decompress") {
+        options.decompress = Some(Vec::new());
+        arg_index = 2;
+    }
+    while arg_index < args.len() {
+        match args[arg_index].as_str() {
+            "--synthetic" => {
+                options.synthetic =
+                    Some(Spec::parse(value(args, arg_index)).unwrap_or_else(|e| fail(program, &e)));
+                arg_index += 2;
+            }
+            "--seed" => {
+                options.seed = value(args, arg_index)
+                    .parse()
+                    .unwrap_or_else(|_| fail(program, "--seed value must be a number"));
+                arg_index += 2;
+            }
+            "--runs" => {
+                options.num_runs = value(args, arg_index)
+                    .parse()
+                    .unwrap_or_else(|_| fail(program, "--runs value must be a number"));
+                if options.num_runs == 0 {
+                    fail(program, "--runs value must be at least 1");
+                }
+                arg_index += 2;
+            }
+            "--min-time" | "--max-time" => {
+                let time = parse_duration(value(args, arg_index)).unwrap_or_else(|| {
+                    fail(
+                        program,
+                        &format!("{} must be a duration like 2s or 500ms", args[arg_index]),
+                    )
+                });
+                if args[arg_index] == "--min-time" {
+                    min_time = Some(time);
+                } else {
+                    max_time = Some(time);
+                }
+                arg_index += 2;
+            }
+            "--target-stderr" => {
+                let percent: f64 = value(args, arg_index)
+                    .parse()
+                    .ok()
+                    .filter(|p: &f64| *p > 0.0)
+                    .unwrap_or_else(|| {
+                        fail(program, "--target-stderr must be a positive percentage")
+                    });
+                target_stderr = percent / 100.0;
+                arg_index += 2;
+            }
+            "--algorithms" => {
+                options
+                    .algorithms
+                    .extend(split_list(value(args, arg_index)));
+                arg_index += 2;
+            }
+            "--exclude" => {
+                options.exclude.extend(split_list(value(args, arg_index)));
+                arg_index += 2;
+            }
+            "--levels" => {
+                parse_levels(value(args, arg_index), &mut options.levels)
+                    .unwrap_or_else(|e| fail(program, &e));
+                arg_index += 2;
+            }
+            "--format" => {
+                options.format = Format::parse(value(args, arg_index)).unwrap_or_else(|| {
+                    fail(program, "--format must be one of markdown, json, csv")
+                });
+                arg_index += 2;
+            }
+            "--output" => {
+                options.output = Some(value(args, arg_index).to_string());
+                arg_index += 2;
+            }
+            "--stat" => {
+                options.stat = Stat::parse(value(args, arg_index)).unwrap_or_else(|| {
+                    fail(
+                        program,
+                        "--stat must be one of total, min, max, mean, median, p95, p99",
+                    )
+                });
+                arg_index += 2;
+            }
+            "--chunk-size" => {
+                options.chunk_size = parse_size(value(args, arg_index))
+                    .filter(|&size| size > 0)
+                    .unwrap_or_else(|| {
+                        fail(
+                            program,
+                            "--chunk-size must be a positive size like 64K or 1M",
+                        )
+                    });
+                arg_index += 2;
+            }
+            "--skip-zero-blocks" => {
+                let size = parse_size(value(args, arg_index))
+                    .filter(|&size| size > 0)
+                    .unwrap_or_else(|| {
+                        fail(
+                            program,
+                            "--skip-zero-blocks must be a positive size like 1M",
+                        )
+                    });
+                options.skip_zero_blocks = Some(size);
+                arg_index += 2;
+            }
+            "--threads" => {
+                options.threads =
+                    parse_threads(value(args, arg_index)).unwrap_or_else(|e| fail(program, &e));
+                arg_index += 2;
+            }
+            "--containers" => {
+                parse_containers(value(args, arg_index), &mut options.containers)
+                    .unwrap_or_else(|e| fail(program, &e));
+                arg_index += 2;
+            }
+            "--mode" => {
+                let modes: Option<Vec<Mode>> = split_list(value(args, arg_index))
+                    .iter()
+                    .map(|mode| Mode::parse(mode))
+                    .collect();
+                options.modes = modes.filter(|m| !m.is_empty()).unwrap_or_else(|| {
+                    fail(program, "--mode must be block, stream or block,stream")
+                });
+                arg_index += 2;
+            }
+            "--io-buffer" => {
+                options.io_buffer = parse_size(value(args, arg_index))
+                    .filter(|&size| size > 0)
+                    .unwrap_or_else(|| {
+                        fail(program, "--io-buffer must be a positive size like 64K")
+                    });
+                arg_index += 2;
+            }
+            "--zstd-dict" => {
+                let size = parse_size(value(args, arg_index))
+                    .filter(|&size| size > 0)
+                    .unwrap_or_else(|| {
+                        fail(program, "--zstd-dict must be a positive size like 112K")
+                    });
+                options.zstd_dict = Some(size);
+                arg_index += 2;
+            }
+            "--dict-sample" => {
+                options.dict_sample = value(args, arg_index)
+                    .parse()
+                    .ok()
+                    .filter(|f| *f > 0.0 && *f <= 1.0)
+                    .unwrap_or_else(|| fail(program, "--dict-sample must be a fraction in (0, 1]"));
+                arg_index += 2;
+            }
+            "--zstd-stream" => {
+                let params =
+                    parse_zstd_stream(value(args, arg_index)).unwrap_or_else(|e| fail(program, &e));
+                options.zstd_streams.push(params);
+                arg_index += 2;
+            }
+            "--pin-cpu" => {
+                let cpu = value(args, arg_index)
+                    .parse()
+                    .unwrap_or_else(|_| fail(program, "--pin-cpu value must be a CPU number"));
+                options.pin_cpu = Some(cpu);
+                arg_index += 2;
+            }
+            "--spin" => {
+                options.spin_ms = value(args, arg_index).parse().unwrap_or_else(|_| {
+                    fail(program, "--spin value must be a number of milliseconds")
+                });
+                arg_index += 2;
+            }
+            "--drift-threshold" => {
+                let percent: f64 = value(args, arg_index)
+                    .parse()
+                    .ok()
+                    .filter(|p: &f64| *p > 0.0)
+                    .unwrap_or_else(|| {
+                        fail(program, "--drift-threshold must be a positive percentage")
+                    });
+                options.drift_threshold = percent / 100.0;
+                arg_index += 2;
+            }
+            "--save-baseline" => {
+                options.save_baseline = Some(value(args, arg_index).to_string());
+                arg_index += 2;
+            }
+            "--compare" => {
+                options.compare = Some(value(args, arg_index).to_string());
+                arg_index += 2;
+            }
+            "--regression-threshold" => {
+                let percent: f64 = value(args, arg_index)
+                    .parse()
+                    .ok()
+                    .filter(|p: &f64| *p >= 0.0)
+                    .unwrap_or_else(|| {
+                        fail(program, "--regression-threshold must be a percentage")
+                    });
+                options.regression_threshold = percent / 100.0;
+                arg_index += 2;
+            }
+            "--require" => {
+                for entry in split_list(value(args, arg_index)) {
+                    let requirement =
+                        Requirement::parse(&entry).unwrap_or_else(|e| fail(program, &e));
+                    options.require.push(requirement);
+                }
+                arg_index += 2;
+            }
+            "--cost-model" => {
+                options.cost_model = Some(
+                    CostModel::parse(value(args, arg_index)).unwrap_or_else(|e| fail(program, &e)),
+                );
+                arg_index += 2;
+            }
+            "--verify-each-run" => {
+                options.verify_each_run = true;
+                arg_index += 1;
+            }
+            "--interop" => {
+                options.interop = true;
+                arg_index += 1;
+            }
+            "--list-algorithms" => {
+                options.list_algorithms = true;
+                arg_index += 1;
+            }
+            "--help" | "-h" => {
+                println!("{}", usage(program));
+                process::exit(0);
+            }
+            arg if !arg.starts_with("--") => {
+                match &mut options.decompress {
+                    Some(files) => files.push(arg.to_string()),
+                    None => options.paths.push(arg.to_string()),
+                }
+                arg_index += 1;
+            }
+            arg => fail(program, &format!("unknown option '{}'", arg)),
+        }
+    }
+
+    if min_time.is_some() || max_time.is_some() {
+        let min_time = min_time.unwrap_or_default();
+        let max_time = max_time.unwrap_or(DEFAULT_MAX_TIME.max(min_time));
+        if max_time < min_time {
+            fail(program, "--max-time must not be shorter than --min-time");
+        }
+        options.budget = Some(RunBudget {
+            min_time,
+            max_time,
+            target_stderr,
+        });
+    }
+
+    let no_input = match &options.decompress {
+        Some(files) => files.is_empty(),
+        None => options.paths.is_empty() && options.synthetic.is_none(),
+    };
+    if !options.paths.is_empty() && options.synthetic.is_some() {
+        fail(program, "give either paths or --synthetic, not both");
+    }
+    if no_input && !options.list_algorithms {
+        eprintln!("{}", usage(program));
+        process::exit(1);
+    }
+
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_duration;
+    use std::time::Duration;
+
+    #[test]
+    fn seconds() {
+        assert_eq!(parse_duration("2"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("2s"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1.5"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration(".25s"), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn milliseconds() {
+        assert_eq!(parse_duration("500ms"), Some(Duration::from_millis(500)));
+        assert_eq!(parse_duration("0.5ms"), Some(Duration::from_micros(500)));
+        assert_eq!(parse_duration("2500ms"), Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn zero_and_whitespace() {
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+        assert_eq!(parse_duration("0ms"), Some(Duration::ZERO));
+        assert_eq!(parse_duration(" 2 s "), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("3 ms"), Some(Duration::from_millis(3)));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        for s in [
+            "", " ", "s", "ms", "abc", "2m", "2min", "1h", "2sms", "1,5s", "--1",
+        ] {
+            assert_eq!(parse_duration(s), None, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn rejects_negative_and_non_finite_durations() {
+        for s in [
+            "-1", "-0.5s", "-100ms", "nan", "NaNs", "inf", "infinity", "1e30",
+        ] {
+            assert_eq!(parse_duration(s), None, "{:?}", s);
+        }
+    }
+}
@@ -0,0 +1,586 @@
+use std::io;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::{Compress, Compression, Crc, Decompress, FlushCompress, FlushDecompress, Status};
+use libdeflater::{CompressionLvl, Compressor, Decompressor};
+use miniz_oxide::deflate::core::{
+    compress, create_comp_flags_from_zip_params, CompressorOxide, TDEFLFlush, TDEFLStatus,
+};
+use miniz_oxide::inflate::core::inflate_flags::{
+    TINFL_FLAG_PARSE_ZLIB_HEADER, TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+};
+use miniz_oxide::inflate::core::{decompress, DecompressorOxide};
+use miniz_oxide::inflate::TINFLStatus;
+
+use super::{deflate_bound, read_to_slice, write_in_pieces, Codec};
+
+/// Size of the gzip header and trailer around a DEFLATE stream.
+const GZIP_OVERHEAD: usize = 18;
+
+/// Minimal gzip header: no file name, no modification time, unknown OS.
+const GZIP_HEADER: [u8; 10] = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+
+/// Framing around a DEFLATE stream.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// Bare DEFLATE (RFC 1951), with no header or checksum.
+    Raw,
+    /// zlib (RFC 1950): 2-byte header and Adler-32 trailer.
+    Zlib,
+    /// gzip (RFC 1952): 10-byte header and CRC-32 plus length trailer.
+    Gzip,
+}
+
+impl Container {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "raw" => Some(Container::Raw),
+            "zlib" => Some(Container::Zlib),
+            "gzip" => Some(Container::Gzip),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Container::Raw => "raw",
+            Container::Zlib => "zlib",
+            Container::Gzip => "gzip",
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn output_too_small() -> io::Error {
+    io::Error::new(io::ErrorKind::WriteZero, "output buffer too small")
+}
+
+/// Length of the gzip header at the start of `input`, including any optional
+/// extra field, file name, comment and header CRC (RFC 1952).
+fn gzip_header_len(input: &[u8]) -> io::Result<usize> {
+    let truncated = || invalid_data("truncated gzip header");
+    if input.len() < 10 {
+        return Err(truncated());
+    }
+    if input[..3] != [0x1f, 0x8b, 8] {
+        return Err(invalid_data("not a gzip stream"));
+    }
+    let flags = input[3];
+    let mut pos = 10;
+    if flags & 0x04 != 0 {
+        let extra = input.get(pos..pos + 2).ok_or_else(truncated)?;
+        pos += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+    }
+    // File name, then comment: zero-terminated strings
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 {
+            let rest = input.get(pos..).ok_or_else(truncated)?;
+            pos += rest.iter().position(|&b| b == 0).ok_or_else(truncated)? + 1;
+        }
+    }
+    if flags & 0x02 != 0 {
+        pos += 2;
+    }
+    if pos > input.len() {
+        return Err(truncated());
+    }
+    Ok(pos)
+}
+
+/// Writes the gzip trailer for `data` at `output[end..end + 8]`.
+fn write_gzip_trailer(output: &mut [u8], end: usize, data: &[u8]) {
+    let mut crc = Crc::new();
+    crc.update(data);
+    output[end..end + 4].copy_from_slice(&crc.sum().to_le_bytes());
+    output[end + 4..end + 8].copy_from_slice(&crc.amount().to_le_bytes());
+}
+
+/// Checks an 8-byte gzip trailer against the decompressed `data`.
+fn check_gzip_trailer(trailer: &[u8], data: &[u8]) -> io::Result<()> {
+    let mut crc = Crc::new();
+    crc.update(data);
+    if trailer[..4] != crc.sum().to_le_bytes() || trailer[4..] != crc.amount().to_le_bytes() {
+        return Err(invalid_data("gzip trailer does not match the data"));
+    }
+    Ok(())
+}
+
+/// Checks the 8-byte trailer of the member at the start of `rest` and that
+/// no second member follows it. Concatenated members are valid gzip, but the
+/// one-shot decoders stop after the first one.
+fn finish_gzip_member(rest: &[u8], data: &[u8]) -> io::Result<()> {
+    let trailer = rest
+        .get(..8)
+        .ok_or_else(|| invalid_data("truncated gzip trailer"))?;
+    check_gzip_trailer(trailer, data)?;
+    if rest[8..].starts_with(&[0x1f, 0x8b]) {
+        return Err(invalid_data("multi-member gzip is not supported"));
+    }
+    Ok(())
+}
+
+/// Splits a gzip buffer into the header, the DEFLATE body and the trailer
+/// for encoders that do not write the container themselves.
+fn gzip_output(output: &mut [u8]) -> io::Result<&mut [u8]> {
+    if output.len() < GZIP_OVERHEAD {
+        return Err(output_too_small());
+    }
+    output[..GZIP_HEADER.len()].copy_from_slice(&GZIP_HEADER);
+    let trailer_start = output.len() - 8;
+    Ok(&mut output[GZIP_HEADER.len()..trailer_start])
+}
+
+/// `flate2` low-level one-shot API, in a raw, zlib or gzip container.
+pub struct Flate2 {
+    name: String,
+    container: Container,
+    compress: Compress,
+    decompress: Decompress,
+}
+
+impl Flate2 {
+    pub fn new(level: Option<i32>, container: Container) -> Self {
+        let (name, level) = match level {
+            Some(level) => (
+                format!("flate2 ({}, level {})", container.name(), level),
+                Compression::new(level as u32),
+            ),
+            None => (
+                format!("flate2 ({})", container.name()),
+                Compression::default(),
+            ),
+        };
+        let zlib = container == Container::Zlib;
+        Self {
+            name,
+            container,
+            compress: Compress::new(level, zlib),
+            decompress: Decompress::new(zlib),
+        }
+    }
+}
+
+impl Codec for Flate2 {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        deflate_bound(len)
+            + match self.container {
+                Container::Raw => 0,
+                Container::Zlib => 6,
+                Container::Gzip => GZIP_OVERHEAD,
+            }
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let gzip = self.container == Container::Gzip;
+        let body = if gzip {
+            gzip_output(output)?
+        } else {
+            &mut output[..]
+        };
+
+        self.compress.reset();
+        loop {
+            let (read, written) = (
+                self.compress.total_in() as usize,
+                self.compress.total_out() as usize,
+            );
+            let status = self.compress.compress(
+                &input[read..],
+                &mut body[written..],
+                FlushCompress::Finish,
+            )?;
+            if status == Status::StreamEnd {
+                break;
+            }
+            if self.compress.total_out() as usize == body.len() {
+                return Err(output_too_small());
+            }
+        }
+        let written = self.compress.total_out() as usize;
+        if !gzip {
+            return Ok(written);
+        }
+
+        let end = GZIP_HEADER.len() + written;
+        write_gzip_trailer(output, end, input);
+        Ok(end + 8)
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let gzip = self.container == Container::Gzip;
+        let body = if gzip {
+            &input[gzip_header_len(input)?..]
+        } else {
+            input
+        };
+
+        self.decompress.reset(self.container == Container::Zlib);
+        let status = self
+            .decompress
+            .decompress(body, output, FlushDecompress::Finish)?;
+        let written = self.decompress.total_out() as usize;
+        if status != Status::StreamEnd {
+            return Err(if written == output.len() {
+                output_too_small()
+            } else {
+                invalid_data("truncated DEFLATE stream")
+            });
+        }
+        if !gzip {
+            return Ok(written);
+        }
+
+        let rest = body
+            .get(self.decompress.total_in() as usize..)
+            .unwrap_or_default();
+        finish_gzip_member(rest, &output[..written])?;
+        Ok(written)
+    }
+}
+
+/// `flate2` `io::Write` encoder and `io::Read` decoder, gzip container.
+pub struct Flate2GzipStream {
+    name: String,
+    level: Compression,
+    io_buffer: usize,
+}
+
+impl Flate2GzipStream {
+    pub fn new(level: Option<i32>, io_buffer: usize) -> Self {
+        let (name, level) = match level {
+            Some(level) => (
+                format!("flate2 (gzip, stream, level {})", level),
+                Compression::new(level as u32),
+            ),
+            None => ("flate2 (gzip, stream)".to_string(), Compression::default()),
+        };
+        Self {
+            name,
+            level,
+            io_buffer,
+        }
+    }
+}
+
+impl Codec for Flate2GzipStream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        deflate_bound(len) + GZIP_OVERHEAD
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let capacity = output.len();
+        let mut encoder = GzEncoder::new(output, self.level);
+        write_in_pieces(&mut encoder, input, self.io_buffer)?;
+        let remaining = encoder.finish()?;
+        Ok(capacity - remaining.len())
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        // Every member, as gzip(1) does: pigz and `cat a.gz b.gz` write several
+        read_to_slice(MultiGzDecoder::new(input), output, self.io_buffer)
+    }
+}
+
+/// `miniz_oxide` low-level API, in a raw, zlib or gzip container. The crate
+/// has no gzip support of its own, so the gzip framing is written here with
+/// the same CRC-32 as `flate2`.
+pub struct MinizOxide {
+    name: String,
+    container: Container,
+    compressor: Box<CompressorOxide>,
+}
+
+impl MinizOxide {
+    pub fn new(level: Option<i32>, container: Container) -> Self {
+        // Raw DEFLATE keeps the original unqualified label
+        let name = match (level, container) {
+            (Some(level), Container::Raw) => format!("miniz_oxide (level {})", level),
+            (None, Container::Raw) => "miniz_oxide".to_string(),
+            (Some(level), container) => {
+                format!("miniz_oxide ({}, level {})", container.name(), level)
+            }
+            (None, container) => format!("miniz_oxide ({})", container.name()),
+        };
+        // A positive window size makes miniz_oxide write the zlib header
+        let window_bits = if container == Container::Zlib { 15 } else { 0 };
+        let flags = create_comp_flags_from_zip_params(level.unwrap_or(6), window_bits, 0);
+        Self {
+            name,
+            container,
+            compressor: Box::new(CompressorOxide::new(flags)),
+        }
+    }
+}
+
+impl Codec for MinizOxide {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        deflate_bound(len)
+            + match self.container {
+                Container::Raw => 0,
+                Container::Zlib => 6,
+                Container::Gzip => GZIP_OVERHEAD,
+            }
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let gzip = self.container == Container::Gzip;
+        let body = if gzip {
+            gzip_output(output)?
+        } else {
+            &mut output[..]
+        };
+
+        self.compressor.reset();
+        let mut input_remaining = input;
+        let mut out_pos = 0;
+        loop {
+            let (status, bytes_in, bytes_out) = compress(
+                &mut self.compressor,
+                input_remaining,
+                &mut body[out_pos..],
+                TDEFLFlush::Finish,
+            );
+            out_pos += bytes_out;
+            input_remaining = &input_remaining[bytes_in..];
+            match status {
+                TDEFLStatus::Done => break,
+                TDEFLStatus::Okay if out_pos < body.len() => continue,
+                TDEFLStatus::Okay => return Err(output_too_small()),
+                _ => return Err(io::Error::other("miniz_oxide compression failed")),
+            }
+        }
+        if !gzip {
+            return Ok(out_pos);
+        }
+
+        let end = GZIP_HEADER.len() + out_pos;
+        write_gzip_trailer(output, end, input);
+        Ok(end + 8)
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let gzip = self.container == Container::Gzip;
+        let body = if gzip {
+            &input[gzip_header_len(input)?..]
+        } else {
+            input
+        };
+
+        let mut flags = TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF;
+        if self.container == Container::Zlib {
+            flags |= TINFL_FLAG_PARSE_ZLIB_HEADER;
+        }
+        let (status, read, written) =
+            decompress(&mut DecompressorOxide::new(), body, output, 0, flags);
+        match status {
+            TINFLStatus::Done => {}
+            TINFLStatus::HasMoreOutput => return Err(output_too_small()),
+            e => {
+                return Err(io::Error::other(format!(
+                    "miniz_oxide decompression failed: {:?}",
+                    e
+                )))
+            }
+        }
+        if gzip {
+            finish_gzip_member(&body[read..], &output[..written])?;
+        }
+        Ok(written)
+    }
+}
+
+/// `libdeflater` (libdeflate bindings), in a raw, zlib or gzip container.
+pub struct Libdeflate {
+    name: String,
+    container: Container,
+    compressor: Compressor,
+    decompressor: Decompressor,
+}
+
+impl Libdeflate {
+    pub fn new(level: Option<i32>, container: Container) -> Self {
+        // Raw DEFLATE keeps the original unqualified label
+        let qualifier = match container {
+            Container::Raw => String::new(),
+            container => format!("{}, ", container.name()),
+        };
+        let (name, lvl) = match level {
+            Some(level) => (
+                format!("libdeflate ({}level {})", qualifier, level),
+                CompressionLvl::new(level).expect("level validated by the registry"),
+            ),
+            None if container == Container::Raw => {
+                ("libdeflate".to_string(), CompressionLvl::default())
+            }
+            None => (
+                format!("libdeflate ({})", container.name()),
+                CompressionLvl::default(),
+            ),
+        };
+        Self {
+            name,
+            container,
+            compressor: Compressor::new(lvl),
+            decompressor: Decompressor::new(),
+        }
+    }
+}
+
+impl Codec for Libdeflate {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        match self.container {
+            Container::Raw => self.compressor.deflate_compress_bound(len),
+            Container::Zlib => self.compressor.zlib_compress_bound(len),
+            Container::Gzip => self.compressor.gzip_compress_bound(len),
+        }
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        match self.container {
+            Container::Raw => self.compressor.deflate_compress(input, output),
+            Container::Zlib => self.compressor.zlib_compress(input, output),
+            Container::Gzip => self.compressor.gzip_compress(input, output),
+        }
+        .map_err(io::Error::other)
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        match self.container {
+            Container::Raw => self.decompressor.deflate_decompress(input, output),
+            Container::Zlib => self.decompressor.zlib_decompress(input, output),
+            Container::Gzip => self.decompressor.gzip_decompress(input, output),
+        }
+        .map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn minimal_header() {
+        assert_eq!(gzip_header_len(&GZIP_HEADER).unwrap(), 10);
+    }
+
+    #[test]
+    fn header_with_extra_field_and_file_name() {
+        // FEXTRA | FNAME, a 3-byte extra field and the name "a.txt"
+        let mut input = vec![0x1f, 0x8b, 8, 0x04 | 0x08, 0, 0, 0, 0, 0, 3];
+        input.extend_from_slice(&[3, 0, b'x', b'y', b'z']);
+        input.extend_from_slice(b"a.txt\0");
+        let header = input.len();
+        input.extend_from_slice(&[0x03, 0x00]);
+        assert_eq!(gzip_header_len(&input).unwrap(), header);
+    }
+
+    #[test]
+    fn header_with_every_optional_field() {
+        // FHCRC | FEXTRA | FNAME | FCOMMENT
+        let mut input = vec![0x1f, 0x8b, 8, 0x02 | 0x04 | 0x08 | 0x10, 0, 0, 0, 0, 0, 3];
+        input.extend_from_slice(&[0, 0]);
+        input.extend_from_slice(b"name\0comment\0");
+        input.extend_from_slice(&[0xab, 0xcd]);
+        assert_eq!(gzip_header_len(&input).unwrap(), input.len());
+    }
+
+    #[test]
+    fn header_written_by_flate2() {
+        let mut encoder = flate2::GzBuilder::new()
+            .filename("data.bin")
+            .extra(vec![1, 2, 3, 4])
+            .comment("benchmark")
+            .write(Vec::new(), Compression::default());
+        encoder.write_all(b"hello").unwrap();
+        let output = encoder.finish().unwrap();
+        let expected = 10 + 2 + 4 + "data.bin\0".len() + "benchmark\0".len();
+        assert_eq!(gzip_header_len(&output).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        assert!(gzip_header_len(&[0x78, 0x9c, 0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        // Right magic, but a compression method other than DEFLATE
+        assert!(gzip_header_len(&[0x1f, 0x8b, 7, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        assert!(gzip_header_len(&[]).is_err());
+        assert!(gzip_header_len(&GZIP_HEADER[..9]).is_err());
+        // FEXTRA without its length, then with a length past the end
+        let mut extra = GZIP_HEADER.to_vec();
+        extra[3] = 0x04;
+        assert!(gzip_header_len(&extra).is_err());
+        extra.extend_from_slice(&[8, 0, 1, 2]);
+        assert!(gzip_header_len(&extra).is_err());
+        // FNAME without its terminating zero
+        let mut name = GZIP_HEADER.to_vec();
+        name[3] = 0x08;
+        name.extend_from_slice(b"a.txt");
+        assert!(gzip_header_len(&name).is_err());
+        // FHCRC with only one byte of the CRC
+        let mut crc = GZIP_HEADER.to_vec();
+        crc[3] = 0x02;
+        crc.push(0);
+        assert!(gzip_header_len(&crc).is_err());
+    }
+
+    /// Two members compressed separately by `codec`, and their contents.
+    fn two_members(codec: &mut dyn Codec) -> (Vec<u8>, Vec<u8>) {
+        let mut input = Vec::new();
+        let mut expected = Vec::new();
+        for part in [&b"first member "[..], b"second member"] {
+            let mut output = vec![0; codec.compress_bound(part.len())];
+            let len = codec.compress_into(part, &mut output).unwrap();
+            input.extend_from_slice(&output[..len]);
+            expected.extend_from_slice(part);
+        }
+        (input, expected)
+    }
+
+    #[test]
+    fn stream_decoder_reads_every_member() {
+        let mut codec = Flate2GzipStream::new(None, 4096);
+        let (input, expected) = two_members(&mut codec);
+        let mut output = vec![0; expected.len()];
+        let len = codec.decompress_into(&input, &mut output).unwrap();
+        assert_eq!(&output[..len], &expected[..]);
+    }
+
+    #[test]
+    fn one_shot_decoders_reject_multi_member_gzip() {
+        let codecs: [Box<dyn Codec>; 2] = [
+            Box::new(Flate2::new(None, Container::Gzip)),
+            Box::new(MinizOxide::new(None, Container::Gzip)),
+        ];
+        for mut codec in codecs {
+            let (input, expected) = two_members(codec.as_mut());
+            let mut output = vec![0; expected.len()];
+            let error = codec.decompress_into(&input, &mut output).unwrap_err();
+            assert_eq!(error.to_string(), "multi-member gzip is not supported");
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use std::io;
+
+use ::lz4::block::CompressionMode;
+use ::lz4::liblz4::BlockChecksum;
+use ::lz4::ContentChecksum;
+use lz4_flex::frame::{FrameDecoder, FrameEncoder, FrameInfo};
+
+use super::{read_to_slice, write_in_pieces, Codec};
+
+/// Worst-case LZ4 frame size with 64 KiB blocks: blocks that do not compress
+/// are stored as they are, behind a 4-byte header and optional 4-byte
+/// checksum, and the frame adds at most 27 bytes of header, end mark and
+/// content checksum.
+fn frame_bound(len: usize) -> usize {
+    len + (len / (64 * 1024) + 1) * 8 + 32
+}
+
+/// The `lz4` crate (liblz4 bindings), block format.
+///
+/// Levels follow the `lz4` CLI and the frame API: negative levels select the
+/// fast mode with that acceleration, 0 to 2 the default fast mode, and 3 to 12
+/// LZ4HC.
+pub struct Lz4Block {
+    name: String,
+    mode: Option<CompressionMode>,
+}
+
+impl Lz4Block {
+    pub fn new(level: Option<i32>) -> Self {
+        let mode = match level {
+            None | Some(0..=2) => None,
+            Some(level) if level < 0 => Some(CompressionMode::FAST(-level)),
+            Some(level) => Some(CompressionMode::HIGHCOMPRESSION(level)),
+        };
+        let name = match level {
+            Some(level) => format!("lz4 (level {})", level),
+            None => "lz4".to_string(),
+        };
+        Self { name, mode }
+    }
+}
+
+impl Codec for Lz4Block {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        ::lz4::block::compress_bound(len).unwrap_or(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        ::lz4::block::compress_to_buffer(input, self.mode, false, output)
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        ::lz4::block::decompress_to_buffer(input, Some(output.len() as i32), output)
+    }
+}
+
+/// Pure Rust `lz4_flex`, block format.
+pub struct Lz4Flex;
+
+impl Codec for Lz4Flex {
+    fn name(&self) -> &str {
+        "lz4_flex"
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        lz4_flex::block::get_maximum_output_size(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        lz4_flex::compress_into(input, output).map_err(io::Error::other)
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        lz4_flex::decompress_into(input, output).map_err(io::Error::other)
+    }
+}
+
+/// The `lz4` crate's frame format through its `io::Write` encoder and
+/// `io::Read` decoder.
+pub struct Lz4Frame {
+    name: String,
+    level: u32,
+    content_checksum: bool,
+    block_checksum: bool,
+    io_buffer: usize,
+}
+
+impl Lz4Frame {
+    /// Stream variant with the crate's default block and content checksums,
+    /// moving `io_buffer` bytes per call.
+    pub fn stream(level: Option<i32>, io_buffer: usize) -> Self {
+        let name = match level {
+            Some(level) => format!("lz4 (stream, level {})", level),
+            None => "lz4 (stream)".to_string(),
+        };
+        Self::build(name, level, true, true, io_buffer)
+    }
+
+    /// Whole chunks in one call, with no block checksums and optionally a
+    /// content checksum over the frame's data.
+    pub fn frame(level: Option<i32>, content_checksum: bool) -> Self {
+        let container = if content_checksum {
+            "frame, checksum"
+        } else {
+            "frame"
+        };
+        let name = match level {
+            Some(level) => format!("lz4 ({}, level {})", container, level),
+            None => format!("lz4 ({})", container),
+        };
+        Self::build(name, level, content_checksum, false, usize::MAX)
+    }
+
+    fn build(
+        name: String,
+        level: Option<i32>,
+        content_checksum: bool,
+        block_checksum: bool,
+        io_buffer: usize,
+    ) -> Self {
+        Self {
+            name,
+            // liblz4 reads this field as a signed int, where negative levels
+            // select the fast mode's acceleration just like the block API
+            level: level.unwrap_or(0) as u32,
+            content_checksum,
+            block_checksum,
+            io_buffer,
+        }
+    }
+}
+
+impl Codec for Lz4Frame {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        frame_bound(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let capacity = output.len();
+        let mut encoder = ::lz4::EncoderBuilder::new()
+            .level(self.level)
+            .checksum(if self.content_checksum {
+                ContentChecksum::ChecksumEnabled
+            } else {
+                ContentChecksum::NoChecksum
+            })
+            .block_checksum(if self.block_checksum {
+                BlockChecksum::BlockChecksumEnabled
+            } else {
+                BlockChecksum::NoBlockChecksum
+            })
+            .build(output)?;
+        write_in_pieces(&mut encoder, input, self.io_buffer)?;
+        let (remaining, result) = encoder.finish();
+        result?;
+        Ok(capacity - remaining.len())
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        read_to_slice(::lz4::Decoder::new(input)?, output, self.io_buffer)
+    }
+}
+
+/// Pure Rust `lz4_flex` frame format.
+pub struct Lz4FlexFrame {
+    name: &'static str,
+    content_checksum: bool,
+    io_buffer: usize,
+}
+
+impl Lz4FlexFrame {
+    /// Stream variant with the crate's default of no checksums, moving
+    /// `io_buffer` bytes per call.
+    pub fn stream(io_buffer: usize) -> Self {
+        Self {
+            name: "lz4_flex (stream)",
+            content_checksum: false,
+            io_buffer,
+        }
+    }
+
+    /// Whole chunks in one call, optionally with a content checksum.
+    pub fn frame(content_checksum: bool) -> Self {
+        Self {
+            name: if content_checksum {
+                "lz4_flex (frame, checksum)"
+            } else {
+                "lz4_flex (frame)"
+            },
+            content_checksum,
+            io_buffer: usize::MAX,
+        }
+    }
+}
+
+impl Codec for Lz4FlexFrame {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        frame_bound(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let capacity = output.len();
+        let frame_info = FrameInfo::new().content_checksum(self.content_checksum);
+        let mut encoder = FrameEncoder::with_frame_info(frame_info, output);
+        write_in_pieces(&mut encoder, input, self.io_buffer)?;
+        let remaining = encoder.finish()?;
+        Ok(capacity - remaining.len())
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        read_to_slice(FrameDecoder::new(input), output, self.io_buffer)
+    }
+}
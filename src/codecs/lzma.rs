@@ -0,0 +1,228 @@
+use std::io;
+
+use xz2::read::XzDecoder;
+use xz2::stream::{Action, Check, LzmaOptions, Status, Stream};
+use xz2::write::XzEncoder;
+
+use super::{read_to_slice, write_in_pieces, Codec};
+
+/// Worst-case LZMA output size. lzma-rs has no uncompressed-chunk fallback,
+/// so incompressible input grows by a few percent rather than a few bytes.
+fn lzma_bound(len: usize) -> usize {
+    len + len / 8 + 1024
+}
+
+/// File format around LZMA data.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// `.xz`: LZMA2 blocks with an index and integrity check.
+    Xz,
+    /// Legacy `.lzma` ("LZMA alone"): a 13-byte header and raw LZMA data.
+    Lzma,
+}
+
+impl Container {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "xz" => Some(Container::Xz),
+            "lzma" => Some(Container::Lzma),
+            _ => None,
+        }
+    }
+}
+
+/// Runs `stream` over all of `input` in a single `lzma_code(LZMA_FINISH)`
+/// pass, returning the output length.
+fn process_all(mut stream: Stream, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+    loop {
+        let (read, written) = (stream.total_in() as usize, stream.total_out() as usize);
+        let status = stream.process(&input[read..], &mut output[written..], Action::Finish)?;
+        if status == Status::StreamEnd {
+            return Ok(stream.total_out() as usize);
+        }
+        // A full output is only too small once liblzma stops making progress:
+        // it may still have a stream's index and footer, or the next stream
+        // of a concatenated `.xz`, to read
+        if (stream.total_in() as usize, stream.total_out() as usize) == (read, written) {
+            if written == output.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "output buffer too small",
+                ));
+            }
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated xz stream",
+            ));
+        }
+    }
+}
+
+/// liblzma via `xz2`'s one-shot `Stream` API at the given preset, in the
+/// `.xz` or legacy `.lzma` container.
+pub struct Xz2 {
+    name: String,
+    preset: u32,
+    container: Container,
+}
+
+impl Xz2 {
+    pub fn new(preset: Option<i32>, container: Container) -> Self {
+        let format = match container {
+            Container::Xz => "lzma",
+            Container::Lzma => "lzma-alone",
+        };
+        let name = match preset {
+            Some(preset) => format!("xz2 ({}, preset {})", format, preset),
+            None => format!("xz2 ({})", format),
+        };
+        Self {
+            name,
+            preset: preset.unwrap_or(6) as u32,
+            container,
+        }
+    }
+}
+
+impl Codec for Xz2 {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        lzma_bound(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let stream = match self.container {
+            Container::Xz => Stream::new_easy_encoder(self.preset, Check::Crc64)?,
+            Container::Lzma => Stream::new_lzma_encoder(&LzmaOptions::new_preset(self.preset)?)?,
+        };
+        process_all(stream, input, output)
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let stream = match self.container {
+            // Concatenated `.xz` streams, as `cat a.xz b.xz` makes, are valid
+            Container::Xz => Stream::new_stream_decoder(u64::MAX, xz2::stream::CONCATENATED)?,
+            Container::Lzma => Stream::new_lzma_decoder(u64::MAX)?,
+        };
+        process_all(stream, input, output)
+    }
+}
+
+/// liblzma via `xz2`'s `io::Write` encoder and `io::Read` decoder.
+pub struct Xz2Stream {
+    name: String,
+    preset: u32,
+    io_buffer: usize,
+}
+
+impl Xz2Stream {
+    pub fn new(preset: Option<i32>, io_buffer: usize) -> Self {
+        let name = match preset {
+            Some(preset) => format!("xz2 (lzma, stream, preset {})", preset),
+            None => "xz2 (lzma, stream)".to_string(),
+        };
+        Self {
+            name,
+            preset: preset.unwrap_or(6) as u32,
+            io_buffer,
+        }
+    }
+}
+
+impl Codec for Xz2Stream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        lzma_bound(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let capacity = output.len();
+        let mut encoder = XzEncoder::new(output, self.preset);
+        write_in_pieces(&mut encoder, input, self.io_buffer)?;
+        let remaining = encoder.finish()?;
+        Ok(capacity - remaining.len())
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        read_to_slice(XzDecoder::new_multi_decoder(input), output, self.io_buffer)
+    }
+}
+
+/// Pure Rust `lzma-rs`, in the legacy `.lzma` or the `.xz` container. Its
+/// only API drives a reader and a writer to completion, so it has no separate
+/// stream variant.
+pub struct LzmaRs {
+    container: Container,
+}
+
+impl LzmaRs {
+    pub fn new(container: Container) -> Self {
+        Self { container }
+    }
+}
+
+impl Codec for LzmaRs {
+    fn name(&self) -> &str {
+        match self.container {
+            Container::Lzma => "lzma-rs",
+            Container::Xz => "lzma-rs (xz)",
+        }
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        lzma_bound(len)
+    }
+
+    fn compress_into(&mut self, mut input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let capacity = output.len();
+        let mut out = output;
+        match self.container {
+            Container::Lzma => lzma_rs::lzma_compress(&mut input, &mut out)?,
+            Container::Xz => lzma_rs::xz_compress(&mut input, &mut out)?,
+        }
+        Ok(capacity - out.len())
+    }
+
+    fn decompress_into(&mut self, mut input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let capacity = output.len();
+        let mut out = output;
+        match self.container {
+            Container::Lzma => lzma_rs::lzma_decompress(&mut input, &mut out),
+            Container::Xz => lzma_rs::xz_decompress(&mut input, &mut out),
+        }
+        .map_err(io::Error::other)?;
+        Ok(capacity - out.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decoders_read_every_stream() {
+        let codecs: [Box<dyn Codec>; 2] = [
+            Box::new(Xz2::new(Some(1), Container::Xz)),
+            Box::new(Xz2Stream::new(Some(1), 4096)),
+        ];
+        for mut codec in codecs {
+            let mut input = Vec::new();
+            let mut expected = Vec::new();
+            for part in [&b"first stream "[..], b"second stream"] {
+                let mut output = vec![0; codec.compress_bound(part.len())];
+                let len = codec.compress_into(part, &mut output).unwrap();
+                input.extend_from_slice(&output[..len]);
+                expected.extend_from_slice(part);
+            }
+            let mut output = vec![0; expected.len()];
+            let len = codec.decompress_into(&input, &mut output).unwrap();
+            assert_eq!(&output[..len], &expected[..], "{}", codec.name());
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::io;
+
+use super::{read_to_slice, write_in_pieces, Codec};
+
+/// Plain memory copy, reported as the upper bound on achievable throughput.
+pub struct Memcpy;
+
+impl Codec for Memcpy {
+    fn name(&self) -> &str {
+        "memcpy"
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        len
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        output[..input.len()].copy_from_slice(input);
+        Ok(input.len())
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        output[..input.len()].copy_from_slice(input);
+        Ok(input.len())
+    }
+}
+
+/// Memory copy through `io::Write` and `io::Read` calls of `io_buffer` bytes,
+/// the baseline for stream variants.
+pub struct MemcpyStream {
+    io_buffer: usize,
+}
+
+impl MemcpyStream {
+    pub fn new(io_buffer: usize) -> Self {
+        Self { io_buffer }
+    }
+}
+
+impl Codec for MemcpyStream {
+    fn name(&self) -> &str {
+        "memcpy (stream)"
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        len
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let mut writer = &mut output[..];
+        write_in_pieces(&mut writer, input, self.io_buffer)?;
+        Ok(input.len())
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        read_to_slice(input, output, self.io_buffer)
+    }
+}
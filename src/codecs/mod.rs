@@ -0,0 +1,710 @@
+//! Compression codecs benchmarked by the harness.
+//!
+//! Each codec wraps one library behind the [`Codec`] trait so that every
+//! algorithm is measured by the same harness loop. Adding a codec means
+//! implementing the trait and listing it in [`families`].
+//!
+//! Most libraries offer both a one-shot block API over whole buffers and a
+//! streaming `io::Write`/`io::Read` API, which can differ in speed and framing.
+//! A family's `build` uses the former and its optional `stream` the latter.
+
+mod deflate;
+mod lz4;
+mod lzma;
+mod memcpy;
+mod snappy;
+mod zstd;
+
+pub use self::zstd::{ZstdParams, STRATEGIES as ZSTD_STRATEGIES};
+
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+/// A compression algorithm as seen by the benchmark harness.
+///
+/// Implementations keep whatever encoder/decoder state the library lets them
+/// reuse, and write into caller-provided buffers so that nothing is allocated
+/// by the harness inside the timed section.
+pub trait Codec {
+    /// Label printed in the results table, e.g. `zstd (level 3)`.
+    fn name(&self) -> &str;
+
+    /// Upper bound on the compressed size of `len` input bytes.
+    fn compress_bound(&mut self, len: usize) -> usize;
+
+    /// Compresses `input` into `output`, returning the compressed length.
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize>;
+
+    /// Decompresses `input` into `output`, returning the decompressed length.
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize>;
+}
+
+/// Builds a stream variant at a level, with the given I/O buffer size.
+type StreamBuild = fn(Option<i32>, usize) -> Box<dyn Codec>;
+
+/// A library, or one configuration of a library, that can be run at one or
+/// more compression levels.
+pub struct Family {
+    /// Key accepted by `--levels`, e.g. `zstd`.
+    pub name: &'static str,
+    /// Levels the library accepts, or `None` if it has no level knob.
+    pub levels: Option<RangeInclusive<i32>>,
+    /// Levels run when `--levels` does not mention this family. `None` builds
+    /// the codec at the library default under its plain label.
+    defaults: Vec<Option<i32>>,
+    /// Container formats the block API can wrap its output in, selected with
+    /// `--containers`; the first is run by default. Empty when the library
+    /// has only one format.
+    pub containers: &'static [&'static str],
+    build: fn(Option<i32>, &'static str) -> Box<dyn Codec>,
+    /// Builds the streaming API variant, moving data through I/O calls of the
+    /// given size.
+    stream: Option<StreamBuild>,
+}
+
+/// Which API of each library to benchmark.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    /// One-shot calls over whole chunks.
+    Block,
+    /// `io::Write` encoders and `io::Read` decoders.
+    Stream,
+}
+
+impl Mode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "block" => Some(Mode::Block),
+            "stream" => Some(Mode::Stream),
+            _ => None,
+        }
+    }
+}
+
+/// Everything that decides which codec specs [`registry`] builds.
+pub struct RegistryConfig {
+    /// Levels per family name, replacing that family's defaults.
+    pub levels: BTreeMap<String, Vec<i32>>,
+    /// Block API containers per family name, or `all`; the key `all` selects
+    /// every container of every family.
+    pub containers: BTreeMap<String, Vec<String>>,
+    pub modes: Vec<Mode>,
+    /// Size of each read or write made by stream variants.
+    pub io_buffer: usize,
+    /// Adds a row per zstd block level using this dictionary.
+    pub zstd_dictionary: Option<Arc<Vec<u8>>>,
+    /// Adds a streaming zstd variant per entry.
+    pub zstd_streams: Vec<ZstdParams>,
+}
+
+fn deflate_container(name: &str) -> deflate::Container {
+    deflate::Container::parse(name).expect("listed in the family's containers")
+}
+
+fn lzma_container(name: &str) -> lzma::Container {
+    lzma::Container::parse(name).expect("listed in the family's containers")
+}
+
+/// All codec families in the order they appear in the results table.
+pub fn families() -> Vec<Family> {
+    vec![
+        Family {
+            name: "memcpy",
+            levels: None,
+            defaults: vec![None],
+            containers: &[],
+            build: |_, _| Box::new(memcpy::Memcpy),
+            stream: Some(|_, io_buffer| Box::new(memcpy::MemcpyStream::new(io_buffer))),
+        },
+        Family {
+            name: "flate2",
+            levels: Some(0..=9),
+            defaults: vec![None],
+            containers: &["gzip", "zlib", "raw"],
+            build: |level, container| {
+                Box::new(deflate::Flate2::new(level, deflate_container(container)))
+            },
+            stream: Some(|level, io_buffer| {
+                Box::new(deflate::Flate2GzipStream::new(level, io_buffer))
+            }),
+        },
+        Family {
+            name: "snap",
+            levels: None,
+            defaults: vec![None],
+            containers: &["raw", "framed"],
+            build: |_, container| match container {
+                "framed" => Box::new(snappy::SnapFrame::framed()),
+                _ => Box::new(snappy::Snap::new()),
+            },
+            stream: Some(|_, io_buffer| Box::new(snappy::SnapFrame::stream(io_buffer))),
+        },
+        Family {
+            name: "lz4",
+            // liblz4 clamps acceleration to LZ4_ACCELERATION_MAX (65537), so
+            // faster levels would only repeat the same row
+            levels: Some(-65537..=12),
+            defaults: vec![None],
+            containers: &["block", "frame", "frame-checksum"],
+            build: |level, container| match container {
+                "frame" => Box::new(self::lz4::Lz4Frame::frame(level, false)),
+                "frame-checksum" => Box::new(self::lz4::Lz4Frame::frame(level, true)),
+                _ => Box::new(self::lz4::Lz4Block::new(level)),
+            },
+            stream: Some(|level, io_buffer| {
+                Box::new(self::lz4::Lz4Frame::stream(level, io_buffer))
+            }),
+        },
+        Family {
+            name: "zstd",
+            levels: Some(::zstd::zstd_safe::min_c_level()..=::zstd::zstd_safe::max_c_level()),
+            defaults: vec![Some(1), Some(3), Some(10)],
+            containers: &[],
+            build: |level, _| Box::new(self::zstd::ZstdBulk::new(level.unwrap_or(3))),
+            stream: Some(|level, io_buffer| {
+                Box::new(self::zstd::ZstdIoStream::new(level.unwrap_or(3), io_buffer))
+            }),
+        },
+        Family {
+            name: "xz2",
+            levels: Some(0..=9),
+            defaults: vec![None],
+            containers: &["xz", "lzma"],
+            build: |level, container| Box::new(lzma::Xz2::new(level, lzma_container(container))),
+            stream: Some(|level, io_buffer| Box::new(lzma::Xz2Stream::new(level, io_buffer))),
+        },
+        Family {
+            name: "lzma-rs",
+            levels: None,
+            defaults: vec![None],
+            // Its xz writer only emits stored LZMA2 chunks (ratio 1.00), so
+            // `lzma-rs (xz)` is left to `--interop` and `decompress`
+            containers: &[],
+            build: |_, _| Box::new(lzma::LzmaRs::new(lzma::Container::Lzma)),
+            stream: None,
+        },
+        Family {
+            name: "miniz_oxide",
+            levels: Some(0..=10),
+            defaults: vec![None],
+            containers: &["raw", "zlib", "gzip"],
+            build: |level, container| {
+                Box::new(deflate::MinizOxide::new(
+                    level,
+                    deflate_container(container),
+                ))
+            },
+            stream: None,
+        },
+        Family {
+            name: "lz4_flex",
+            levels: None,
+            defaults: vec![None],
+            containers: &["block", "frame", "frame-checksum"],
+            build: |_, container| match container {
+                "frame" => Box::new(self::lz4::Lz4FlexFrame::frame(false)),
+                "frame-checksum" => Box::new(self::lz4::Lz4FlexFrame::frame(true)),
+                _ => Box::new(self::lz4::Lz4Flex),
+            },
+            stream: Some(|_, io_buffer| Box::new(self::lz4::Lz4FlexFrame::stream(io_buffer))),
+        },
+        Family {
+            name: "libdeflate",
+            levels: Some(0..=12),
+            defaults: vec![None],
+            containers: &["raw", "zlib", "gzip"],
+            build: |level, container| {
+                Box::new(deflate::Libdeflate::new(
+                    level,
+                    deflate_container(container),
+                ))
+            },
+            stream: None,
+        },
+    ]
+}
+
+/// Codecs from different libraries that produce and consume the same format,
+/// for `--interop`.
+pub struct InteropGroup {
+    pub format: &'static str,
+    pub codecs: Vec<Box<dyn Codec>>,
+}
+
+/// Every format with more than one implementation, each at its default level.
+pub fn interop_groups() -> Vec<InteropGroup> {
+    use deflate::Container as Deflate;
+    use lzma::Container as Lzma;
+    vec![
+        InteropGroup {
+            format: "DEFLATE",
+            codecs: vec![
+                Box::new(deflate::Flate2::new(None, Deflate::Raw)),
+                Box::new(deflate::MinizOxide::new(None, Deflate::Raw)),
+                Box::new(deflate::Libdeflate::new(None, Deflate::Raw)),
+            ],
+        },
+        InteropGroup {
+            format: "zlib",
+            codecs: vec![
+                Box::new(deflate::Flate2::new(None, Deflate::Zlib)),
+                Box::new(deflate::MinizOxide::new(None, Deflate::Zlib)),
+                Box::new(deflate::Libdeflate::new(None, Deflate::Zlib)),
+            ],
+        },
+        InteropGroup {
+            format: "gzip",
+            codecs: vec![
+                Box::new(deflate::Flate2::new(None, Deflate::Gzip)),
+                Box::new(deflate::MinizOxide::new(None, Deflate::Gzip)),
+                Box::new(deflate::Libdeflate::new(None, Deflate::Gzip)),
+            ],
+        },
+        InteropGroup {
+            format: "LZ4 block",
+            codecs: vec![
+                Box::new(self::lz4::Lz4Block::new(None)),
+                Box::new(self::lz4::Lz4Flex),
+            ],
+        },
+        InteropGroup {
+            format: "LZ4 frame",
+            codecs: vec![
+                Box::new(self::lz4::Lz4Frame::frame(None, false)),
+                Box::new(self::lz4::Lz4Frame::frame(None, true)),
+                Box::new(self::lz4::Lz4FlexFrame::frame(false)),
+                Box::new(self::lz4::Lz4FlexFrame::frame(true)),
+            ],
+        },
+        InteropGroup {
+            format: "xz",
+            codecs: vec![
+                Box::new(lzma::Xz2::new(None, Lzma::Xz)),
+                Box::new(lzma::LzmaRs::new(Lzma::Xz)),
+            ],
+        },
+        InteropGroup {
+            format: "lzma",
+            codecs: vec![
+                Box::new(lzma::Xz2::new(None, Lzma::Lzma)),
+                Box::new(lzma::LzmaRs::new(Lzma::Lzma)),
+            ],
+        },
+    ]
+}
+
+/// A decoder offered by the `decompress` subcommand, labelled by library and
+/// API rather than by the level it would compress at.
+pub struct Decoder {
+    pub name: &'static str,
+    pub codec: Box<dyn Codec>,
+}
+
+/// A compressed file format recognised by its leading magic bytes.
+pub struct DecodeFormat {
+    pub name: &'static str,
+    magic: fn(&[u8]) -> bool,
+    reference: fn(&[u8]) -> io::Result<Vec<u8>>,
+    decoders: fn(usize) -> Vec<Decoder>,
+}
+
+impl DecodeFormat {
+    /// Decodes `data` through an `io::Read` decoder into a growing buffer,
+    /// which gives the decompressed size the benchmarked decoders need and
+    /// the contents they are verified against. Untimed.
+    pub fn reference(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        (self.reference)(data)
+    }
+
+    /// Every implementation that reads the format, with stream decoders
+    /// reading `io_buffer` bytes per call.
+    pub fn decoders(&self, io_buffer: usize) -> Vec<Decoder> {
+        (self.decoders)(io_buffer)
+    }
+}
+
+fn read_all(mut reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output)?;
+    Ok(output)
+}
+
+/// Formats recognised by [`detect`], most specific magic first.
+fn decode_formats() -> Vec<DecodeFormat> {
+    use deflate::Container as Deflate;
+    use lzma::Container as Lzma;
+    vec![
+        DecodeFormat {
+            name: "zstd",
+            magic: |data| data.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]),
+            reference: |data| ::zstd::stream::decode_all(data),
+            decoders: |io_buffer| {
+                vec![
+                    Decoder {
+                        name: "zstd (bulk)",
+                        codec: Box::new(self::zstd::ZstdBulk::new(3)),
+                    },
+                    Decoder {
+                        name: "zstd (stream)",
+                        codec: Box::new(self::zstd::ZstdIoStream::new(3, io_buffer)),
+                    },
+                ]
+            },
+        },
+        DecodeFormat {
+            name: "xz",
+            magic: |data| data.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0]),
+            reference: |data| read_all(xz2::read::XzDecoder::new_multi_decoder(data)),
+            decoders: |io_buffer| {
+                vec![
+                    Decoder {
+                        name: "xz2 (lzma)",
+                        codec: Box::new(lzma::Xz2::new(None, Lzma::Xz)),
+                    },
+                    Decoder {
+                        name: "xz2 (lzma, stream)",
+                        codec: Box::new(lzma::Xz2Stream::new(None, io_buffer)),
+                    },
+                    Decoder {
+                        name: "lzma-rs (xz)",
+                        codec: Box::new(lzma::LzmaRs::new(Lzma::Xz)),
+                    },
+                ]
+            },
+        },
+        DecodeFormat {
+            name: "LZ4 frame",
+            magic: |data| data.starts_with(&[0x04, 0x22, 0x4d, 0x18]),
+            reference: |data| read_all(lz4_flex::frame::FrameDecoder::new(data)),
+            decoders: |io_buffer| {
+                vec![
+                    Decoder {
+                        name: "lz4 (frame)",
+                        codec: Box::new(self::lz4::Lz4Frame::frame(None, false)),
+                    },
+                    Decoder {
+                        name: "lz4 (stream)",
+                        codec: Box::new(self::lz4::Lz4Frame::stream(None, io_buffer)),
+                    },
+                    Decoder {
+                        name: "lz4_flex (frame)",
+                        codec: Box::new(self::lz4::Lz4FlexFrame::frame(false)),
+                    },
+                    Decoder {
+                        name: "lz4_flex (stream)",
+                        codec: Box::new(self::lz4::Lz4FlexFrame::stream(io_buffer)),
+                    },
+                ]
+            },
+        },
+        DecodeFormat {
+            name: "snappy framed",
+            magic: |data| data.starts_with(b"\xff\x06\x00\x00sNaPpY"),
+            reference: |data| read_all(snap::read::FrameDecoder::new(data)),
+            decoders: |io_buffer| {
+                vec![
+                    Decoder {
+                        name: "snap (snappy, framed)",
+                        codec: Box::new(snappy::SnapFrame::framed()),
+                    },
+                    Decoder {
+                        name: "snap (snappy, stream)",
+                        codec: Box::new(snappy::SnapFrame::stream(io_buffer)),
+                    },
+                ]
+            },
+        },
+        DecodeFormat {
+            name: "gzip",
+            magic: |data| data.starts_with(&[0x1f, 0x8b, 8]),
+            reference: |data| read_all(flate2::read::MultiGzDecoder::new(data)),
+            decoders: |io_buffer| {
+                vec![
+                    Decoder {
+                        name: "flate2 (gzip)",
+                        codec: Box::new(deflate::Flate2::new(None, Deflate::Gzip)),
+                    },
+                    Decoder {
+                        name: "flate2 (gzip, stream)",
+                        codec: Box::new(deflate::Flate2GzipStream::new(None, io_buffer)),
+                    },
+                    Decoder {
+                        name: "miniz_oxide (gzip)",
+                        codec: Box::new(deflate::MinizOxide::new(None, Deflate::Gzip)),
+                    },
+                    Decoder {
+                        name: "libdeflate (gzip)",
+                        codec: Box::new(deflate::Libdeflate::new(None, Deflate::Gzip)),
+                    },
+                ]
+            },
+        },
+        DecodeFormat {
+            // "LZMA alone" has no magic; its header starts with the lc/lp/pb
+            // byte, which is 0x5d for every preset, and a dictionary size
+            // whose low two bytes are zero for every preset size
+            name: "lzma",
+            magic: |data| data.len() >= 13 && data[..3] == [0x5d, 0, 0],
+            reference: |data| {
+                let stream = xz2::stream::Stream::new_lzma_decoder(u64::MAX)?;
+                read_all(xz2::read::XzDecoder::new_stream(data, stream))
+            },
+            decoders: |_| {
+                vec![
+                    Decoder {
+                        name: "xz2 (lzma-alone)",
+                        codec: Box::new(lzma::Xz2::new(None, Lzma::Lzma)),
+                    },
+                    Decoder {
+                        name: "lzma-rs",
+                        codec: Box::new(lzma::LzmaRs::new(Lzma::Lzma)),
+                    },
+                ]
+            },
+        },
+        DecodeFormat {
+            // Compression method 8 and a header checksum that is a multiple
+            // of 31; checked last, as two bytes match by chance more easily
+            name: "zlib",
+            magic: |data| {
+                data.len() >= 2
+                    && data[0] & 0x0f == 8
+                    && data[0] >> 4 <= 7
+                    && u16::from_be_bytes([data[0], data[1]]) % 31 == 0
+            },
+            reference: |data| read_all(flate2::read::ZlibDecoder::new(data)),
+            decoders: |_| {
+                vec![
+                    Decoder {
+                        name: "flate2 (zlib)",
+                        codec: Box::new(deflate::Flate2::new(None, Deflate::Zlib)),
+                    },
+                    Decoder {
+                        name: "miniz_oxide (zlib)",
+                        codec: Box::new(deflate::MinizOxide::new(None, Deflate::Zlib)),
+                    },
+                    Decoder {
+                        name: "libdeflate (zlib)",
+                        codec: Box::new(deflate::Libdeflate::new(None, Deflate::Zlib)),
+                    },
+                ]
+            },
+        },
+    ]
+}
+
+/// The format of a compressed file, from its first bytes.
+pub fn detect(data: &[u8]) -> Option<DecodeFormat> {
+    decode_formats()
+        .into_iter()
+        .find(|format| (format.magic)(data))
+}
+
+/// Names of every format [`detect`] recognises.
+pub fn decode_format_names() -> Vec<&'static str> {
+    decode_formats().iter().map(|format| format.name).collect()
+}
+
+/// Every decoder of every format, for `decompress --list-algorithms`.
+pub fn all_decoders(io_buffer: usize) -> Vec<(&'static str, Decoder)> {
+    decode_formats()
+        .into_iter()
+        .flat_map(|format| {
+            let name = format.name;
+            format
+                .decoders(io_buffer)
+                .into_iter()
+                .map(move |decoder| (name, decoder))
+        })
+        .collect()
+}
+
+/// One configured codec, able to build fresh instances of itself so that
+/// every worker thread can own its encoder/decoder state.
+#[derive(Clone)]
+pub struct CodecSpec {
+    name: String,
+    build: Arc<dyn Fn() -> Box<dyn Codec> + Send + Sync>,
+}
+
+impl CodecSpec {
+    pub fn new(build: impl Fn() -> Box<dyn Codec> + Send + Sync + 'static) -> Self {
+        let name = build().name().to_string();
+        Self {
+            name,
+            build: Arc::new(build),
+        }
+    }
+
+    /// The label of every codec this spec builds.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn build(&self) -> Box<dyn Codec> {
+        (self.build)()
+    }
+}
+
+/// Builds every codec spec in table order. Each family contributes its block
+/// rows, one per selected container and level, and then its stream rows, one
+/// per level; levels come from `config.levels` or the family's defaults.
+/// After the plain zstd rows come one row per zstd level using the
+/// dictionary, if any, and one streaming variant per `zstd_streams` entry.
+pub fn registry(config: &RegistryConfig) -> Result<Vec<CodecSpec>, String> {
+    let families = families();
+    for (name, requested) in &config.levels {
+        let family = families
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| format!("--levels: unknown codec family '{}'", name))?;
+        let range = family
+            .levels
+            .as_ref()
+            .ok_or_else(|| format!("--levels: {} has no compression levels", name))?;
+        if let Some(level) = requested.iter().find(|l| !range.contains(l)) {
+            return Err(format!(
+                "--levels: {} level {} is outside {}..{}",
+                name,
+                level,
+                range.start(),
+                range.end()
+            ));
+        }
+    }
+
+    for (name, requested) in &config.containers {
+        if name == "all" {
+            continue;
+        }
+        let family = families
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| format!("--containers: unknown codec family '{}'", name))?;
+        if family.containers.is_empty() {
+            return Err(format!("--containers: {} has only one format", name));
+        }
+        if let Some(container) = requested
+            .iter()
+            .find(|c| *c != "all" && !family.containers.contains(&c.as_str()))
+        {
+            return Err(format!(
+                "--containers: {} has no '{}' container (choose from {})",
+                name,
+                container,
+                family.containers.join(", ")
+            ));
+        }
+    }
+
+    let block = config.modes.contains(&Mode::Block);
+    let mut specs = Vec::new();
+    for family in &families {
+        let family_levels = match config.levels.get(family.name) {
+            Some(requested) => requested.iter().map(|&level| Some(level)).collect(),
+            None => family.defaults.clone(),
+        };
+        if block {
+            let build = family.build;
+            for container in selected_containers(family, &config.containers) {
+                specs.extend(
+                    family_levels
+                        .iter()
+                        .map(|&level| CodecSpec::new(move || build(level, container))),
+                );
+            }
+        }
+        if let (Some(stream), true) = (family.stream, config.modes.contains(&Mode::Stream)) {
+            let io_buffer = config.io_buffer;
+            specs.extend(
+                family_levels
+                    .iter()
+                    .map(|&level| CodecSpec::new(move || stream(level, io_buffer))),
+            );
+        }
+        if family.name == "zstd" {
+            if let (Some(dictionary), true) = (&config.zstd_dictionary, block) {
+                for level in family_levels.iter().map(|level| level.unwrap_or(3)) {
+                    self::zstd::ZstdDict::new(level, dictionary)
+                        .map_err(|e| format!("zstd dictionary: {}", e))?;
+                    let dictionary = Arc::clone(dictionary);
+                    specs.push(CodecSpec::new(move || {
+                        Box::new(
+                            self::zstd::ZstdDict::new(level, &dictionary).expect("validated above"),
+                        )
+                    }));
+                }
+            }
+            for params in &config.zstd_streams {
+                // Fail here rather than inside a worker thread
+                self::zstd::ZstdStream::new(params).map_err(|e| format!("--zstd-stream: {}", e))?;
+                let params = params.clone();
+                specs.push(CodecSpec::new(move || {
+                    Box::new(self::zstd::ZstdStream::new(&params).expect("validated above"))
+                }));
+            }
+        }
+    }
+    Ok(specs)
+}
+
+/// The containers `requested` for `family`, in the family's order, or its
+/// default container when none are.
+fn selected_containers(
+    family: &Family,
+    requested: &BTreeMap<String, Vec<String>>,
+) -> Vec<&'static str> {
+    let Some(&default) = family.containers.first() else {
+        return vec![""];
+    };
+    match requested.get(family.name).or_else(|| requested.get("all")) {
+        Some(names) if names.iter().any(|n| n == "all") => family.containers.to_vec(),
+        Some(names) => family
+            .containers
+            .iter()
+            .copied()
+            .filter(|c| names.iter().any(|n| n == c))
+            .collect(),
+        None => vec![default],
+    }
+}
+
+/// Drains `reader` into `output` with reads of at most `io_buffer` bytes,
+/// failing if `output` is too small.
+fn read_to_slice<R: Read>(mut reader: R, output: &mut [u8], io_buffer: usize) -> io::Result<usize> {
+    let mut pos = 0;
+    loop {
+        if pos == output.len() {
+            let mut probe = [0u8; 1];
+            return match reader.read(&mut probe)? {
+                0 => Ok(pos),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "output buffer too small",
+                )),
+            };
+        }
+        let end = output.len().min(pos.saturating_add(io_buffer));
+        match reader.read(&mut output[pos..end]) {
+            Ok(0) => return Ok(pos),
+            Ok(n) => pos += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Feeds `input` to `writer` in writes of at most `io_buffer` bytes, the way
+/// an application streaming from a file or socket would.
+fn write_in_pieces<W: Write>(writer: &mut W, input: &[u8], io_buffer: usize) -> io::Result<()> {
+    input
+        .chunks(io_buffer)
+        .try_for_each(|piece| writer.write_all(piece))
+}
+
+/// zlib's `compressBound()`: worst-case size of a raw DEFLATE stream.
+fn deflate_bound(len: usize) -> usize {
+    len + (len >> 12) + (len >> 14) + (len >> 25) + 13
+}
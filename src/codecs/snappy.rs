@@ -0,0 +1,92 @@
+use std::io;
+
+use snap::raw::{max_compress_len, Decoder, Encoder};
+use snap::read::FrameDecoder;
+use snap::write::FrameEncoder;
+
+use super::{read_to_slice, write_in_pieces, Codec};
+
+/// Largest amount of input the Snappy framing format puts in one chunk.
+const FRAME_CHUNK: usize = 64 * 1024;
+
+/// Snappy raw (unframed) block format.
+pub struct Snap {
+    encoder: Encoder,
+    decoder: Decoder,
+}
+
+impl Snap {
+    pub fn new() -> Self {
+        Self {
+            encoder: Encoder::new(),
+            decoder: Decoder::new(),
+        }
+    }
+}
+
+impl Codec for Snap {
+    fn name(&self) -> &str {
+        "snap (snappy)"
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        max_compress_len(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        Ok(self.encoder.compress(input, output)?)
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        Ok(self.decoder.decompress(input, output)?)
+    }
+}
+
+/// Snappy framing format through `snap::write`/`snap::read`, which splits the
+/// stream into chunks that each carry a CRC32C of their data.
+pub struct SnapFrame {
+    name: &'static str,
+    io_buffer: usize,
+}
+
+impl SnapFrame {
+    /// Stream variant moving `io_buffer` bytes per call.
+    pub fn stream(io_buffer: usize) -> Self {
+        Self {
+            name: "snap (snappy, stream)",
+            io_buffer,
+        }
+    }
+
+    /// Whole chunks in one call.
+    pub fn framed() -> Self {
+        Self {
+            name: "snap (snappy, framed)",
+            io_buffer: usize::MAX,
+        }
+    }
+}
+
+impl Codec for SnapFrame {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        // Stream identifier, then an 8-byte header and checksum per chunk;
+        // chunks that do not compress are stored as they are
+        10 + len + (len / FRAME_CHUNK + 1) * 8
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let capacity = output.len();
+        let mut encoder = FrameEncoder::new(output);
+        write_in_pieces(&mut encoder, input, self.io_buffer)?;
+        let remaining = encoder.into_inner().map_err(|e| e.into_error())?;
+        Ok(capacity - remaining.len())
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        read_to_slice(FrameDecoder::new(input), output, self.io_buffer)
+    }
+}
@@ -0,0 +1,282 @@
+use std::io;
+
+use ::zstd::bulk::{Compressor, Decompressor};
+use ::zstd::stream::raw::{
+    CParameter, DParameter, Decoder, Encoder, InBuffer, Operation, OutBuffer,
+};
+use ::zstd::zstd_safe::Strategy;
+
+use super::{read_to_slice, write_in_pieces, Codec};
+
+/// zstd one-shot block API at a fixed compression level.
+pub struct ZstdBulk {
+    name: String,
+    level: i32,
+}
+
+impl ZstdBulk {
+    pub fn new(level: i32) -> Self {
+        Self {
+            name: format!("zstd (level {})", level),
+            level,
+        }
+    }
+}
+
+impl Codec for ZstdBulk {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        ::zstd::zstd_safe::compress_bound(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        ::zstd::bulk::compress_to_buffer(input, output, self.level)
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        ::zstd::bulk::decompress_to_buffer(input, output)
+    }
+}
+
+/// zstd through the `zstd::stream` `io::Write` encoder and `io::Read`
+/// decoder at a fixed compression level.
+pub struct ZstdIoStream {
+    name: String,
+    level: i32,
+    io_buffer: usize,
+}
+
+impl ZstdIoStream {
+    pub fn new(level: i32, io_buffer: usize) -> Self {
+        Self {
+            name: format!("zstd (stream, level {})", level),
+            level,
+            io_buffer,
+        }
+    }
+}
+
+impl Codec for ZstdIoStream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        ::zstd::zstd_safe::compress_bound(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let capacity = output.len();
+        let mut encoder = ::zstd::stream::write::Encoder::new(output, self.level)?;
+        write_in_pieces(&mut encoder, input, self.io_buffer)?;
+        let remaining = encoder.finish()?;
+        Ok(capacity - remaining.len())
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        let decoder = ::zstd::stream::read::Decoder::with_buffer(input)?;
+        read_to_slice(decoder, output, self.io_buffer)
+    }
+}
+
+/// zstd one-shot block API with a trained dictionary loaded into both
+/// contexts, so small chunks share context without carrying it in each frame.
+pub struct ZstdDict {
+    name: String,
+    compressor: Compressor<'static>,
+    decompressor: Decompressor<'static>,
+}
+
+impl ZstdDict {
+    pub fn new(level: i32, dictionary: &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            name: format!("zstd (level {}, dict)", level),
+            compressor: Compressor::with_dictionary(level, dictionary)?,
+            decompressor: Decompressor::with_dictionary(dictionary)?,
+        })
+    }
+}
+
+impl Codec for ZstdDict {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        ::zstd::zstd_safe::compress_bound(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        self.compressor.compress_to_buffer(input, output)
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        self.decompressor.decompress_to_buffer(input, output)
+    }
+}
+
+/// zstd strategies by the name accepted on the command line.
+pub const STRATEGIES: &[(&str, Strategy)] = &[
+    ("fast", Strategy::ZSTD_fast),
+    ("dfast", Strategy::ZSTD_dfast),
+    ("greedy", Strategy::ZSTD_greedy),
+    ("lazy", Strategy::ZSTD_lazy),
+    ("lazy2", Strategy::ZSTD_lazy2),
+    ("btlazy2", Strategy::ZSTD_btlazy2),
+    ("btopt", Strategy::ZSTD_btopt),
+    ("btultra", Strategy::ZSTD_btultra),
+    ("btultra2", Strategy::ZSTD_btultra2),
+];
+
+/// Advanced parameters for [`ZstdStream`]. `None` leaves the library default
+/// that `level` implies.
+#[derive(Clone, Debug, Default)]
+pub struct ZstdParams {
+    pub level: Option<i32>,
+    /// Background compression threads (`ZSTD_c_nbWorkers`); 0 compresses on
+    /// the calling thread.
+    pub workers: u32,
+    pub long_distance_matching: bool,
+    pub window_log: Option<u32>,
+    /// Index into [`STRATEGIES`].
+    pub strategy: Option<usize>,
+}
+
+impl ZstdParams {
+    fn label(&self) -> String {
+        let mut parts = vec![format!("level {}", self.level.unwrap_or(3))];
+        if self.workers > 0 {
+            parts.push(format!("workers {}", self.workers));
+        }
+        if self.long_distance_matching {
+            parts.push("ldm".to_string());
+        }
+        if let Some(window_log) = self.window_log {
+            parts.push(format!("wlog {}", window_log));
+        }
+        if let Some(strategy) = self.strategy {
+            parts.push(STRATEGIES[strategy].0.to_string());
+        }
+        format!("zstd (stream, {})", parts.join(", "))
+    }
+}
+
+/// zstd streaming API with advanced parameters: multithreaded compression,
+/// long-distance matching, window log and strategy. Each chunk is one frame.
+pub struct ZstdStream {
+    name: String,
+    encoder: Encoder<'static>,
+    decoder: Decoder<'static>,
+}
+
+impl ZstdStream {
+    pub fn new(params: &ZstdParams) -> io::Result<Self> {
+        let mut encoder = Encoder::new(params.level.unwrap_or(3))?;
+        if params.workers > 0 {
+            encoder.set_parameter(CParameter::NbWorkers(params.workers))?;
+        }
+        if params.long_distance_matching {
+            encoder.set_parameter(CParameter::EnableLongDistanceMatching(true))?;
+        }
+        if let Some(window_log) = params.window_log {
+            encoder.set_parameter(CParameter::WindowLog(window_log))?;
+        }
+        if let Some(strategy) = params.strategy {
+            encoder.set_parameter(CParameter::Strategy(STRATEGIES[strategy].1))?;
+        }
+        // Long-distance matching raises the window to 128 MiB, beyond the
+        // decoder's default limit
+        let mut decoder = Decoder::new()?;
+        decoder.set_parameter(DParameter::WindowLogMax(31))?;
+        Ok(Self {
+            name: params.label(),
+            encoder,
+            decoder,
+        })
+    }
+}
+
+fn output_full() -> io::Error {
+    io::Error::new(io::ErrorKind::WriteZero, "output buffer too small")
+}
+
+impl Codec for ZstdStream {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn compress_bound(&mut self, len: usize) -> usize {
+        ::zstd::zstd_safe::compress_bound(len)
+    }
+
+    fn compress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        self.encoder.reinit()?;
+        self.encoder
+            .set_pledged_src_size(Some(input.len() as u64))?;
+        let mut input = InBuffer::around(input);
+        let mut output = OutBuffer::around(output);
+        while input.pos() < input.src.len() {
+            if output.pos() == output.capacity() {
+                return Err(output_full());
+            }
+            self.encoder.run(&mut input, &mut output)?;
+        }
+        // Ending the frame blocks until every worker's job has been flushed
+        while self.encoder.finish(&mut output, true)? > 0 {
+            if output.pos() == output.capacity() {
+                return Err(output_full());
+            }
+        }
+        Ok(output.pos())
+    }
+
+    fn decompress_into(&mut self, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        self.decoder.reinit()?;
+        let mut input = InBuffer::around(input);
+        let mut output = OutBuffer::around(output);
+        loop {
+            let before = (input.pos(), output.pos());
+            if self.decoder.run(&mut input, &mut output)? == 0 {
+                return Ok(output.pos());
+            }
+            // A full output buffer may still leave the frame epilogue to
+            // consume, so only a call that makes no progress is an error
+            if (input.pos(), output.pos()) == before {
+                return Err(if output.pos() == output.capacity() {
+                    output_full()
+                } else {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated zstd frame")
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decoders_read_every_frame() {
+        let codecs: [Box<dyn Codec>; 2] = [
+            Box::new(ZstdBulk::new(3)),
+            Box::new(ZstdIoStream::new(3, 4096)),
+        ];
+        for mut codec in codecs {
+            let mut input = Vec::new();
+            let mut expected = Vec::new();
+            for part in [&b"first frame "[..], b"second frame"] {
+                let mut output = vec![0; codec.compress_bound(part.len())];
+                let len = codec.compress_into(part, &mut output).unwrap();
+                input.extend_from_slice(&output[..len]);
+                expected.extend_from_slice(part);
+            }
+            let mut output = vec![0; expected.len()];
+            let len = codec.decompress_into(&input, &mut output).unwrap();
+            assert_eq!(&output[..len], &expected[..], "{}", codec.name());
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Total cost of ownership per codec for `--cost-model`: storing the
+//! compressed bytes for the retention period, compressing them once and
+//! decompressing them on every read.
+
+use serde::{Deserialize, Serialize};
+
+use crate::report::{self, Measurement, ZeroBlockSkip};
+
+/// Prices and workload given to `--cost-model`.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct CostModel {
+    /// Dollars per GiB stored for a month.
+    pub storage_per_gib_month: f64,
+    /// Dollars per CPU core busy for an hour.
+    pub cpu_per_core_hour: f64,
+    /// Times each stripe is read, i.e. decompressed, over the retention
+    /// period.
+    pub reads: f64,
+    pub retention_months: f64,
+}
+
+impl CostModel {
+    /// Parses comma-separated `storage=$/GiB-month`, `cpu=$/core-hour`,
+    /// `reads=N` and `retention=MONTHS` entries, all required.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let (mut storage, mut cpu, mut reads, mut retention) = (None, None, None, None);
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key, value) = entry.split_once('=').ok_or_else(|| {
+                format!("--cost-model entry '{}' must look like KEY=VALUE", entry)
+            })?;
+            let value: f64 = value
+                .trim()
+                .trim_start_matches('$')
+                .parse()
+                .ok()
+                .filter(|v: &f64| *v >= 0.0 && v.is_finite())
+                .ok_or_else(|| {
+                    format!("--cost-model {} must be a non-negative number", key.trim())
+                })?;
+            let slot = match key.trim() {
+                "storage" => &mut storage,
+                "cpu" => &mut cpu,
+                "reads" => &mut reads,
+                "retention" => &mut retention,
+                key => return Err(format!("--cost-model: unknown parameter '{}'", key)),
+            };
+            *slot = Some(value);
+        }
+        let missing = |name: &str| format!("--cost-model needs {}=", name);
+        Ok(Self {
+            storage_per_gib_month: storage.ok_or_else(|| missing("storage"))?,
+            cpu_per_core_hour: cpu.ok_or_else(|| missing("cpu"))?,
+            reads: reads.ok_or_else(|| missing("reads"))?,
+            retention_months: retention.ok_or_else(|| missing("retention"))?,
+        })
+    }
+
+    /// Cost of keeping one TiB uncompressed for the retention period.
+    pub fn uncompressed(&self) -> f64 {
+        TIB_IN_GIB * self.storage_per_gib_month * self.retention_months
+    }
+}
+
+const TIB_IN_GIB: f64 = 1024.0;
+const TIB_IN_MIB: f64 = 1024.0 * 1024.0;
+
+/// One codec's projected cost per TiB of uncompressed data.
+#[derive(Serialize, Deserialize)]
+pub struct CodecCost {
+    pub label: String,
+    pub storage: f64,
+    pub compress_cpu: f64,
+    pub decompress_cpu: f64,
+    pub total: f64,
+}
+
+/// The model and every codec that completed its round trips, cheapest first.
+#[derive(Serialize, Deserialize)]
+pub struct CostReport {
+    pub model: CostModel,
+    /// Whether `--skip-zero-blocks` blocks were priced as free.
+    #[serde(default)]
+    pub skipped_zero_blocks: bool,
+    pub codecs: Vec<CodecCost>,
+}
+
+/// Core-hours to push one TiB through at `throughput` MiB/s on `threads`
+/// threads, counting every thread as busy for the whole run.
+fn core_hours(throughput: f64, threads: usize) -> f64 {
+    TIB_IN_MIB / throughput * threads as f64 / 3600.0
+}
+
+/// Prices every codec of `results` that did not fail under `model`, from
+/// its ratio and aggregate throughput, and ranks them by total cost. `memcpy`
+/// is left out, since the uncompressed cost in the heading already covers it.
+/// With `zero_blocks`, the skipped blocks cost nothing to store or process,
+/// so every cost shrinks by the share of the input that was benchmarked.
+pub fn rank(
+    results: &[Measurement],
+    model: &CostModel,
+    zero_blocks: Option<&ZeroBlockSkip>,
+) -> CostReport {
+    let scale = zero_blocks.map_or(1.0, ZeroBlockSkip::scale);
+    let mut codecs: Vec<CodecCost> = results
+        .iter()
+        .filter(|m| !m.is_reference())
+        .filter(|m| m.results.failure.is_none() && !m.results.input_sizes.is_empty())
+        .map(|measurement| {
+            let (compress, decompress) = report::aggregate_throughput(&measurement.results);
+            let threads = measurement.threads;
+            let storage = model.uncompressed() / (report::ratio(&measurement.results) * scale);
+            let compress_cpu = core_hours(compress, threads) * model.cpu_per_core_hour / scale;
+            let decompress_cpu =
+                core_hours(decompress, threads) * model.cpu_per_core_hour * model.reads / scale;
+            CodecCost {
+                label: measurement.label(),
+                storage,
+                compress_cpu,
+                decompress_cpu,
+                total: storage + compress_cpu + decompress_cpu,
+            }
+        })
+        .collect();
+    codecs.sort_by(|a, b| a.total.total_cmp(&b.total));
+    CostReport {
+        model: *model,
+        skipped_zero_blocks: zero_blocks.is_some(),
+        codecs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL: &str = "storage=0.023,cpu=0.04,reads=10,retention=36";
+
+    #[test]
+    fn parses_every_parameter() {
+        let model = CostModel::parse(FULL).unwrap();
+        assert_eq!(model.storage_per_gib_month, 0.023);
+        assert_eq!(model.cpu_per_core_hour, 0.04);
+        assert_eq!(model.reads, 10.0);
+        assert_eq!(model.retention_months, 36.0);
+        assert!((model.uncompressed() - 1024.0 * 0.023 * 36.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn order_spaces_dollar_signs_and_zero() {
+        let model =
+            CostModel::parse(" retention = 12 , reads=0, cpu=$0.5 ,storage=$0.01,").unwrap();
+        assert_eq!(model.storage_per_gib_month, 0.01);
+        assert_eq!(model.cpu_per_core_hour, 0.5);
+        assert_eq!(model.reads, 0.0);
+        assert_eq!(model.retention_months, 12.0);
+    }
+
+    #[test]
+    fn a_repeated_parameter_keeps_the_last_value() {
+        let model = CostModel::parse(&format!("{},reads=3", FULL)).unwrap();
+        assert_eq!(model.reads, 3.0);
+    }
+
+    #[test]
+    fn every_parameter_is_required() {
+        assert_eq!(
+            CostModel::parse("").err().unwrap(),
+            "--cost-model needs storage="
+        );
+        for (missing, list) in [
+            ("storage", "cpu=0.04,reads=10,retention=36"),
+            ("cpu", "storage=0.023,reads=10,retention=36"),
+            ("reads", "storage=0.023,cpu=0.04,retention=36"),
+            ("retention", "storage=0.023,cpu=0.04,reads=10"),
+        ] {
+            assert_eq!(
+                CostModel::parse(list).err().unwrap(),
+                format!("--cost-model needs {}=", missing)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        assert!(CostModel::parse(&format!("{},storage", FULL)).is_err());
+        assert!(CostModel::parse(&format!("{},disk=1", FULL))
+            .err()
+            .unwrap()
+            .contains("unknown parameter 'disk'"));
+        for value in ["", "abc", "-1", "nan", "inf", "1e400", "$", "0.02/GiB"] {
+            let list = format!("storage={},cpu=0.04,reads=10,retention=36", value);
+            assert_eq!(
+                CostModel::parse(&list).err().as_deref(),
+                Some("--cost-model storage must be a non-negative number"),
+                "{:?}",
+                value
+            );
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! CPU time and resource usage around timed sections, from `getrusage` and
+//! `clock_gettime`.
+//!
+//! Both are process-wide rather than per-thread: `--threads` workers and the
+//! threads some codecs start themselves (zstd's `workers=N`) do their work
+//! outside the timing thread.
+
+use std::mem::MaybeUninit;
+
+use serde::{Deserialize, Serialize};
+
+/// How far CPU time may stray from `threads` times the wall time before the
+/// report warns about it.
+pub const DIVERGENCE_THRESHOLD: f64 = 0.10;
+
+/// Resource usage of one direction of a codec, summed over its timed runs.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CpuUsage {
+    /// CPU time of every thread, from `CLOCK_PROCESS_CPUTIME_ID`.
+    pub cpu_ns: u64,
+    pub user_ns: u64,
+    pub sys_ns: u64,
+    pub voluntary_switches: u64,
+    pub involuntary_switches: u64,
+    pub minor_faults: u64,
+    pub major_faults: u64,
+}
+
+impl CpuUsage {
+    pub fn merge(&mut self, other: CpuUsage) {
+        self.cpu_ns += other.cpu_ns;
+        self.user_ns += other.user_ns;
+        self.sys_ns += other.sys_ns;
+        self.voluntary_switches += other.voluntary_switches;
+        self.involuntary_switches += other.involuntary_switches;
+        self.minor_faults += other.minor_faults;
+        self.major_faults += other.major_faults;
+    }
+}
+
+fn timeval_ns(tv: libc::timeval) -> u64 {
+    tv.tv_sec as u64 * 1_000_000_000 + tv.tv_usec as u64 * 1000
+}
+
+/// Counters at the start of a timed section. Take it right before starting
+/// the clock and finish it right after stopping it.
+pub struct CpuSnapshot {
+    cpu_ns: u64,
+    usage: libc::rusage,
+}
+
+impl CpuSnapshot {
+    pub fn now() -> Self {
+        let mut usage = MaybeUninit::<libc::rusage>::zeroed();
+        let mut time = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: both calls only write to the structs passed in, and cannot
+        // fail for these arguments
+        let usage = unsafe {
+            libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr());
+            libc::clock_gettime(libc::CLOCK_PROCESS_CPUTIME_ID, &mut time);
+            usage.assume_init()
+        };
+        Self {
+            cpu_ns: time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64,
+            usage,
+        }
+    }
+
+    /// What the process used since this snapshot, added to `total`.
+    pub fn finish(self, total: &mut CpuUsage) {
+        let end = CpuSnapshot::now();
+        let (a, b) = (&self.usage, &end.usage);
+        total.merge(CpuUsage {
+            cpu_ns: end.cpu_ns - self.cpu_ns,
+            user_ns: timeval_ns(b.ru_utime).saturating_sub(timeval_ns(a.ru_utime)),
+            sys_ns: timeval_ns(b.ru_stime).saturating_sub(timeval_ns(a.ru_stime)),
+            voluntary_switches: (b.ru_nvcsw - a.ru_nvcsw) as u64,
+            involuntary_switches: (b.ru_nivcsw - a.ru_nivcsw) as u64,
+            minor_faults: (b.ru_minflt - a.ru_minflt) as u64,
+            major_faults: (b.ru_majflt - a.ru_majflt) as u64,
+        });
+    }
+}
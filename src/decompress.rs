@@ -0,0 +1,162 @@
+//! The `decompress` subcommand: decoder-only benchmarks on files that were
+//! compressed elsewhere, such as stripes copied out of a storage backend.
+
+use std::fs;
+use std::io;
+
+use crate::cli::Options;
+use crate::codecs::{self, Decoder};
+use crate::harness::{self, BenchConfig, DecodeMeasurement};
+use crate::host::HostInfo;
+use crate::report::{self, DecodeInput, DecodeReport};
+use crate::select::glob_match;
+
+/// Whether `--algorithms`/`--exclude` keep `decoder`.
+fn selected(options: &Options, decoder: &Decoder) -> bool {
+    (options.algorithms.is_empty()
+        || options
+            .algorithms
+            .iter()
+            .any(|p| glob_match(p, decoder.name)))
+        && !options.exclude.iter().any(|p| glob_match(p, decoder.name))
+}
+
+/// Detects each file's format, decodes it once for reference, then times
+/// every selected decoder of that format on it and writes the report.
+pub fn run(files: &[String], options: &Options) {
+    let decoders = codecs::all_decoders(options.io_buffer);
+    if options.list_algorithms {
+        for (format, decoder) in &decoders {
+            println!("{}: {}", format, decoder.name);
+        }
+        return;
+    }
+    for pattern in &options.algorithms {
+        if !decoders.iter().any(|(_, d)| glob_match(pattern, d.name)) {
+            eprintln!(
+                "Error: '{}' does not match any decoder (see decompress --list-algorithms)",
+                pattern
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let config = BenchConfig {
+        num_runs: options.num_runs,
+        budget: None,
+        chunk_size: usize::MAX,
+        verify_each_run: options.verify_each_run,
+        counters: None,
+    };
+    progress!("Number of runs per decoder: {}", options.num_runs);
+    progress!();
+
+    let mut inputs = Vec::new();
+    let mut results: Vec<DecodeMeasurement> = Vec::new();
+    for path in files {
+        let data = fs::read(path).unwrap_or_else(|e| {
+            eprintln!("Error: failed to read {}: {}", path, e);
+            std::process::exit(1);
+        });
+        let mut input = DecodeInput {
+            path: path.clone(),
+            format: None,
+            compressed_size: data.len(),
+            decompressed_size: 0,
+            skipped: None,
+        };
+        let Some(format) = codecs::detect(&data) else {
+            progress!("File: {}: unrecognised format, skipped", path);
+            input.skipped = Some(format!(
+                "unrecognised format (expected one of {})",
+                codecs::decode_format_names().join(", ")
+            ));
+            inputs.push(input);
+            continue;
+        };
+        input.format = Some(format.name.to_string());
+        let expected = match format.reference(&data) {
+            Ok(expected) => expected,
+            Err(e) => {
+                progress!(
+                    "File: {}: {} reference decode failed, skipped",
+                    path,
+                    format.name
+                );
+                input.skipped = Some(format!("reference decode failed: {}", e));
+                inputs.push(input);
+                continue;
+            }
+        };
+        input.decompressed_size = expected.len();
+        progress!(
+            "File: {} ({}, {} bytes -> {} bytes)",
+            path,
+            format.name,
+            data.len(),
+            expected.len()
+        );
+        inputs.push(input);
+
+        let mut output = vec![0u8; expected.len()];
+        for mut decoder in format.decoders(options.io_buffer) {
+            if !selected(options, &decoder) {
+                continue;
+            }
+            let index = match results
+                .iter()
+                .position(|m| m.format == format.name && m.decoder == decoder.name)
+            {
+                Some(index) => index,
+                None => {
+                    results.push(DecodeMeasurement {
+                        format: format.name.to_string(),
+                        decoder: decoder.name.to_string(),
+                        files: 0,
+                        compressed_size: 0,
+                        decompressed_size: 0,
+                        decompress_ns: Vec::new(),
+                        failures: Vec::new(),
+                    });
+                    results.len() - 1
+                }
+            };
+            harness::decode(
+                decoder.codec.as_mut(),
+                path,
+                &data,
+                &expected,
+                &mut output,
+                &config,
+                &mut results[index],
+            );
+        }
+    }
+
+    if inputs.iter().all(|input| input.skipped.is_some()) {
+        eprintln!("Error: none of the files could be decoded");
+        std::process::exit(1);
+    }
+
+    let report = DecodeReport {
+        inputs,
+        num_runs: options.num_runs,
+        host: HostInfo::collect(),
+        results,
+    };
+    let written = match &options.output {
+        Some(path) => fs::File::create(path).and_then(|mut file| {
+            report::write_decode(options.format, &mut file, &report, options.stat)
+        }),
+        None => report::write_decode(
+            options.format,
+            &mut io::stdout().lock(),
+            &report,
+            options.stat,
+        ),
+    };
+    if let Err(e) = written {
+        eprintln!("Error: failed to write report: {}", e);
+        std::process::exit(1);
+    }
+}
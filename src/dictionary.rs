@@ -0,0 +1,115 @@
+//! Training a zstd dictionary on a sample of the input's chunks.
+
+use std::io::{self, Seek, SeekFrom};
+use std::time::Instant;
+
+use crate::codecs::Codec;
+use crate::input::Source;
+use crate::report::{DictionaryTraining, HeldOut};
+
+/// Whether chunk `index` of the input is in a `fraction` sample: it is
+/// whenever it carries the running sample count past another whole chunk,
+/// which spaces the sample evenly.
+fn sampled(index: usize, fraction: f64) -> bool {
+    (index as f64 * fraction).floor() != ((index + 1) as f64 * fraction).floor()
+}
+
+/// Trains a dictionary of at most `max_size` bytes on `fraction` of the
+/// `chunk_size` chunks of `sources`, spread evenly across them. Each source
+/// is chunked on its own, as the benchmark does. All-zero chunks are left
+/// out of the sample, since they teach the trainer nothing.
+pub fn train(
+    sources: &[Source],
+    chunk_size: usize,
+    max_size: usize,
+    fraction: f64,
+) -> io::Result<(Vec<u8>, DictionaryTraining)> {
+    let mut chunks = 0;
+    let mut sample_data = Vec::new();
+    let mut sample_sizes = Vec::new();
+    for source in sources {
+        let mut input = source.open()?;
+        for offset in (0..source.size).step_by(chunk_size) {
+            let index = chunks;
+            chunks += 1;
+            if !sampled(index, fraction) {
+                continue;
+            }
+            let start = sample_data.len();
+            let len = chunk_size.min(source.size - offset);
+            sample_data.resize(start + len, 0);
+            input.seek(SeekFrom::Start(offset as u64))?;
+            let read = crate::read_full(&mut input, &mut sample_data[start..])?;
+            sample_data.truncate(start + read);
+            if sample_data[start..].iter().all(|&b| b == 0) {
+                sample_data.truncate(start);
+            } else {
+                sample_sizes.push(read);
+            }
+        }
+    }
+    if sample_sizes.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no non-zero chunks to train on; raise --dict-sample",
+        ));
+    }
+
+    let start = Instant::now();
+    let dictionary = ::zstd::dict::from_continuous(&sample_data, &sample_sizes, max_size)?;
+    let training_ns = start.elapsed().as_nanos() as u64;
+
+    let training = DictionaryTraining {
+        max_size,
+        size: dictionary.len(),
+        chunks,
+        samples: sample_sizes.len(),
+        sample_bytes: sample_data.len(),
+        training_ns,
+        held_out: Vec::new(),
+    };
+    Ok((dictionary, training))
+}
+
+/// Compresses, untimed, every chunk of `sources` that [`train`] left out of
+/// the sample with each of `codecs`, so that dictionary rows can be judged
+/// on data the dictionary has not seen. All-zero chunks are left out here
+/// too.
+pub fn held_out(
+    sources: &[Source],
+    chunk_size: usize,
+    fraction: f64,
+    codecs: &mut [Box<dyn Codec>],
+) -> io::Result<Vec<HeldOut>> {
+    let mut held_out: Vec<HeldOut> = codecs
+        .iter()
+        .map(|codec| HeldOut {
+            algorithm: codec.name().to_string(),
+            input_bytes: 0,
+            compressed_bytes: 0,
+        })
+        .collect();
+    let mut index = 0;
+    let mut chunk = vec![0; chunk_size];
+    let mut output = Vec::new();
+    for source in sources {
+        let mut input = source.open()?;
+        loop {
+            let read = crate::read_full(&mut input, &mut chunk)?;
+            if read == 0 {
+                break;
+            }
+            index += 1;
+            let chunk = &chunk[..read];
+            if sampled(index - 1, fraction) || chunk.iter().all(|&b| b == 0) {
+                continue;
+            }
+            for (codec, total) in codecs.iter_mut().zip(&mut held_out) {
+                output.resize(codec.compress_bound(read), 0);
+                total.input_bytes += read;
+                total.compressed_bytes += codec.compress_into(chunk, &mut output)?;
+            }
+        }
+    }
+    Ok(held_out)
+}
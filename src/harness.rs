@@ -0,0 +1,996 @@
+//! Generic timing loop shared by every codec.
+
+use std::hint::black_box;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Barrier, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::codecs::{Codec, CodecSpec, InteropGroup};
+use crate::cpu::{CpuSnapshot, CpuUsage};
+use crate::memory::{MemoryUsage, Tracker};
+use crate::perf::{self, Counters, PerfCounts};
+use crate::stats;
+
+/// Per-run measurements for one codec, stored column-wise. Serializes as a
+/// [`ResultsRecord`] holding a list of [`RunRecord`]s.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "ResultsRecord", from = "ResultsRecord")]
+pub struct BenchmarkResults {
+    pub input_sizes: Vec<usize>,
+    pub compressed_sizes: Vec<usize>,
+    pub compress_times: Vec<Duration>,
+    pub decompress_times: Vec<Duration>,
+    pub compress_memory: MemoryUsage,
+    pub decompress_memory: MemoryUsage,
+    /// CPU time and resource usage over the timed runs.
+    pub compress_cpu: CpuUsage,
+    pub decompress_cpu: CpuUsage,
+    /// Hardware counters over the timed runs, if they could be read.
+    pub compress_perf: Option<PerfCounts>,
+    pub decompress_perf: Option<PerfCounts>,
+    /// Timed runs made on each batch, which a [`RunBudget`] can vary.
+    pub runs_per_batch: Vec<usize>,
+    /// Whether `--max-time` stopped any batch before its measurement was
+    /// stable or its minimum time and runs were reached.
+    pub hit_max_time: bool,
+    /// Why the codec stopped being measured, if it errored or failed round-trip
+    /// verification. Runs recorded before the failure are kept.
+    pub failure: Option<String>,
+}
+
+impl BenchmarkResults {
+    pub fn new() -> Self {
+        Self {
+            input_sizes: Vec::new(),
+            compressed_sizes: Vec::new(),
+            compress_times: Vec::new(),
+            decompress_times: Vec::new(),
+            compress_memory: MemoryUsage::default(),
+            decompress_memory: MemoryUsage::default(),
+            compress_cpu: CpuUsage::default(),
+            decompress_cpu: CpuUsage::default(),
+            compress_perf: None,
+            decompress_perf: None,
+            runs_per_batch: Vec::new(),
+            hit_max_time: false,
+            failure: None,
+        }
+    }
+
+    pub fn merge(&mut self, other: BenchmarkResults) {
+        self.input_sizes.extend(other.input_sizes);
+        self.compressed_sizes.extend(other.compressed_sizes);
+        self.compress_times.extend(other.compress_times);
+        self.decompress_times.extend(other.decompress_times);
+        self.compress_memory.merge(other.compress_memory);
+        self.decompress_memory.merge(other.decompress_memory);
+        self.compress_cpu.merge(other.compress_cpu);
+        self.decompress_cpu.merge(other.decompress_cpu);
+        if let Some(counts) = other.compress_perf {
+            perf::merge(&mut self.compress_perf, counts);
+        }
+        if let Some(counts) = other.decompress_perf {
+            perf::merge(&mut self.decompress_perf, counts);
+        }
+        self.runs_per_batch.extend(other.runs_per_batch);
+        self.hit_max_time |= other.hit_max_time;
+        if self.failure.is_none() {
+            self.failure = other.failure;
+        }
+    }
+
+    /// Folds the batches merged into these results, whether of one file or
+    /// of a whole corpus, into runs over all of them: run `k` becomes every
+    /// batch's run `k` with sizes and times added up. There is then one
+    /// sample per pass over the input, not one per batch, so the spread
+    /// between runs is timing noise rather than the difference between
+    /// batches. Runs beyond the fewest any batch made, which a [`RunBudget`]
+    /// can leave, are dropped. Failed results are left as they are.
+    pub fn sum_batches(&mut self) {
+        let runs = self.runs_per_batch.iter().sum::<usize>();
+        if self.failure.is_some() || self.runs_per_batch.len() < 2 || self.input_sizes.len() != runs
+        {
+            return;
+        }
+        let runs = self.runs_per_batch.iter().copied().min().unwrap_or(0);
+        let mut input_sizes = vec![0; runs];
+        let mut compressed_sizes = vec![0; runs];
+        let mut compress_times = vec![Duration::ZERO; runs];
+        let mut decompress_times = vec![Duration::ZERO; runs];
+        let mut offset = 0;
+        for &batch_runs in &self.runs_per_batch {
+            for run in 0..runs {
+                input_sizes[run] += self.input_sizes[offset + run];
+                compressed_sizes[run] += self.compressed_sizes[offset + run];
+                compress_times[run] += self.compress_times[offset + run];
+                decompress_times[run] += self.decompress_times[offset + run];
+            }
+            offset += batch_runs;
+        }
+        self.input_sizes = input_sizes;
+        self.compressed_sizes = compressed_sizes;
+        self.compress_times = compress_times;
+        self.decompress_times = decompress_times;
+    }
+
+    /// Per-run compression throughput in MiB/s.
+    pub fn compress_throughputs(&self) -> Vec<f64> {
+        throughputs(&self.input_sizes, &self.compress_times)
+    }
+
+    /// Per-run decompression throughput in MiB/s.
+    pub fn decompress_throughputs(&self) -> Vec<f64> {
+        throughputs(&self.input_sizes, &self.decompress_times)
+    }
+}
+
+/// Throughput in MiB/s of each run that moved `sizes[i]` bytes in `times[i]`.
+pub fn throughputs(sizes: &[usize], times: &[Duration]) -> Vec<f64> {
+    sizes
+        .iter()
+        .zip(times)
+        .map(|(&size, time)| (size as f64 / (1024.0 * 1024.0)) / time.as_secs_f64())
+        .collect()
+}
+
+/// One timed compress/decompress round trip, as written to JSON reports.
+#[derive(Serialize, Deserialize)]
+pub struct RunRecord {
+    pub input_size: usize,
+    pub compressed_size: usize,
+    pub compress_ns: u64,
+    pub decompress_ns: u64,
+}
+
+/// Serialized form of [`BenchmarkResults`].
+#[derive(Serialize, Deserialize)]
+pub struct ResultsRecord {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+    #[serde(default)]
+    pub compress_memory: MemoryUsage,
+    #[serde(default)]
+    pub decompress_memory: MemoryUsage,
+    #[serde(default)]
+    pub compress_cpu: CpuUsage,
+    #[serde(default)]
+    pub decompress_cpu: CpuUsage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress_perf: Option<PerfCounts>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decompress_perf: Option<PerfCounts>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runs_per_batch: Vec<usize>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hit_max_time: bool,
+    pub runs: Vec<RunRecord>,
+}
+
+impl From<BenchmarkResults> for ResultsRecord {
+    fn from(results: BenchmarkResults) -> Self {
+        let runs = (0..results.input_sizes.len())
+            .map(|run| RunRecord {
+                input_size: results.input_sizes[run],
+                compressed_size: results.compressed_sizes[run],
+                compress_ns: results.compress_times[run].as_nanos() as u64,
+                decompress_ns: results.decompress_times[run].as_nanos() as u64,
+            })
+            .collect();
+        ResultsRecord {
+            failure: results.failure,
+            compress_memory: results.compress_memory,
+            decompress_memory: results.decompress_memory,
+            compress_cpu: results.compress_cpu,
+            decompress_cpu: results.decompress_cpu,
+            compress_perf: results.compress_perf,
+            decompress_perf: results.decompress_perf,
+            runs_per_batch: results.runs_per_batch,
+            hit_max_time: results.hit_max_time,
+            runs,
+        }
+    }
+}
+
+impl From<ResultsRecord> for BenchmarkResults {
+    fn from(record: ResultsRecord) -> Self {
+        let mut results = BenchmarkResults::new();
+        results.failure = record.failure;
+        results.compress_memory = record.compress_memory;
+        results.decompress_memory = record.decompress_memory;
+        results.compress_cpu = record.compress_cpu;
+        results.decompress_cpu = record.decompress_cpu;
+        results.compress_perf = record.compress_perf;
+        results.decompress_perf = record.decompress_perf;
+        results.runs_per_batch = record.runs_per_batch;
+        results.hit_max_time = record.hit_max_time;
+        for run in record.runs {
+            results.input_sizes.push(run.input_size);
+            results.compressed_sizes.push(run.compressed_size);
+            results
+                .compress_times
+                .push(Duration::from_nanos(run.compress_ns));
+            results
+                .decompress_times
+                .push(Duration::from_nanos(run.decompress_ns));
+        }
+        results
+    }
+}
+
+/// Writes every byte of `buf` so the OS backs it with physical pages before
+/// the timed section. `black_box` stops the compiler from eliding the stores
+/// into memory it knows came zeroed from the allocator.
+fn prefault(buf: &mut [u8]) {
+    black_box(buf).fill(0);
+}
+
+/// Bytes copied per pass of [`benchmark_memcpy`]: well past any CPU cache.
+const MEMCPY_SIZE: usize = 64 * 1024 * 1024;
+const MEMCPY_PASSES: usize = 5;
+
+/// Memory bandwidth in MiB/s of the fastest of a few `copy_from_slice` passes
+/// over a buffer too large for the caches. The fastest pass rather than the
+/// mean, so that one interrupted pass does not read as drift.
+pub fn benchmark_memcpy() -> f64 {
+    let source = vec![0x5au8; MEMCPY_SIZE];
+    let mut target = vec![0u8; MEMCPY_SIZE];
+    prefault(&mut target);
+    let fastest = (0..MEMCPY_PASSES)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(&mut target[..]).copy_from_slice(black_box(&source));
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default();
+    throughputs(&[MEMCPY_SIZE], &[fastest])[0]
+}
+
+/// Time limits that replace a fixed run count: a codec keeps running until
+/// it has spent `min_time` and its per-run throughput has a relative standard
+/// error at most `target_stderr`, but stops once it has spent `max_time`.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct RunBudget {
+    #[serde(rename = "min_time_ns", with = "duration_ns")]
+    pub min_time: Duration,
+    #[serde(rename = "max_time_ns", with = "duration_ns")]
+    pub max_time: Duration,
+    pub target_stderr: f64,
+}
+
+/// (De)serializes a [`Duration`] as whole nanoseconds, like the per-run times.
+mod duration_ns {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_nanos() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_nanos)
+    }
+}
+
+/// Knobs for [`benchmark`] that stay fixed for a whole invocation.
+pub struct BenchConfig {
+    /// Timed runs per batch, or the minimum when `budget` is set.
+    pub num_runs: usize,
+    /// Adaptive run count for [`benchmark`] and [`benchmark_parallel`].
+    pub budget: Option<RunBudget>,
+    /// Size of the independently compressed units `data` is split into.
+    pub chunk_size: usize,
+    /// Also verify the round trip after every timed run, not just warm-up.
+    pub verify_each_run: bool,
+    /// Hardware counters wrapped around every timed section, when the `perf`
+    /// feature is on and the host exposes them. Opened before any codec or
+    /// worker thread exists, so that every thread inherits them.
+    pub counters: Option<Counters>,
+}
+
+/// Whether [`benchmark`] should make another timed run, given the runs in
+/// `results` so far and when the first of them started. Flags
+/// [`BenchmarkResults::hit_max_time`] when the budget cuts a codec short.
+fn more_runs(config: &BenchConfig, results: &mut BenchmarkResults, start: Instant) -> bool {
+    let runs = results.input_sizes.len();
+    let Some(budget) = &config.budget else {
+        return runs < config.num_runs;
+    };
+    if runs == 0 {
+        return true;
+    }
+    let elapsed = start.elapsed();
+    let unstable = || {
+        [
+            results.compress_throughputs(),
+            results.decompress_throughputs(),
+        ]
+        .iter()
+        .any(|samples| {
+            !stats::relative_stderr(samples).is_some_and(|stderr| stderr <= budget.target_stderr)
+        })
+    };
+    let wanted = runs < config.num_runs || elapsed < budget.min_time || unstable();
+    if wanted && elapsed >= budget.max_time {
+        results.hit_max_time = true;
+        return false;
+    }
+    wanted
+}
+
+fn start_counters(config: &BenchConfig) {
+    if let Some(counters) = &config.counters {
+        counters.start();
+    }
+}
+
+fn stop_counters(config: &BenchConfig, total: &mut Option<PerfCounts>) {
+    if let Some(counters) = &config.counters {
+        counters.stop(total);
+    }
+}
+
+/// Checks that `decompressed` reproduces `original` byte for byte.
+fn verify(original: &[u8], decompressed: &[u8]) -> Result<(), String> {
+    if decompressed.len() != original.len() {
+        return Err(format!(
+            "round trip produced {} bytes, expected {}",
+            decompressed.len(),
+            original.len()
+        ));
+    }
+    match original.iter().zip(decompressed).position(|(a, b)| a != b) {
+        Some(offset) => Err(format!("round trip differs at byte {}", offset)),
+        None => Ok(()),
+    }
+}
+
+/// Compresses each `chunk_size` piece of `data` on its own, packing the
+/// outputs back to back into `compressed` and recording where each landed in
+/// `extents`. Returns the total compressed size.
+fn compress_chunks(
+    codec: &mut dyn Codec,
+    data: &[u8],
+    chunk_size: usize,
+    compressed: &mut [u8],
+    extents: &mut Vec<Range<usize>>,
+) -> Result<usize, String> {
+    extents.clear();
+    let mut offset = 0;
+    for chunk in data.chunks(chunk_size) {
+        let len = codec
+            .compress_into(black_box(chunk), &mut compressed[offset..])
+            .map_err(|e| format!("compression failed: {}", e))?;
+        extents.push(offset..offset + len);
+        offset += len;
+    }
+    Ok(offset)
+}
+
+/// Decompresses every extent written by [`compress_chunks`] into its own
+/// `chunk_size` slot of `decompressed`. Returns the total decompressed size.
+fn decompress_chunks(
+    codec: &mut dyn Codec,
+    compressed: &[u8],
+    extents: &[Range<usize>],
+    chunk_size: usize,
+    decompressed: &mut [u8],
+) -> Result<usize, String> {
+    let mut total = 0;
+    for (extent, slot) in extents.iter().zip(decompressed.chunks_mut(chunk_size)) {
+        total += codec
+            .decompress_into(black_box(&compressed[extent.clone()]), slot)
+            .map_err(|e| format!("decompression failed: {}", e))?;
+    }
+    Ok(total)
+}
+
+/// Benchmarks `codec` on `data`: one untimed, verified warm-up round trip,
+/// then `config.num_runs` timed compress/decompress passes, or as many as
+/// `config.budget` calls for. Each pass treats every `config.chunk_size`
+/// piece of `data` as an independent unit, the way a block device compresses
+/// stripes, and is timed as a whole.
+///
+/// Codec errors and round trips that do not reproduce `data` are recorded in
+/// [`BenchmarkResults::failure`] rather than aborting the whole benchmark.
+pub fn benchmark(codec: &mut dyn Codec, data: &[u8], config: &BenchConfig) -> BenchmarkResults {
+    let mut results = BenchmarkResults::new();
+    if let Err(failure) = run_benchmark(codec, data, config, &mut results) {
+        progress!("  [{}] FAILED: {}", codec.name(), failure);
+        results.failure = Some(failure);
+    }
+    results
+}
+
+fn run_benchmark(
+    codec: &mut dyn Codec,
+    data: &[u8],
+    config: &BenchConfig,
+    results: &mut BenchmarkResults,
+) -> Result<(), String> {
+    let name = codec.name().to_string();
+    let num_runs = config.num_runs;
+    let chunk_size = config.chunk_size;
+    progress!("  [{}] Preparing buffers ...", name);
+
+    // 1. Pre-allocate buffers, with room for every chunk's worst case
+    let bound = data
+        .chunks(chunk_size)
+        .map(|chunk| codec.compress_bound(chunk.len()))
+        .sum();
+    let mut compressed = vec![0u8; bound];
+    let mut decompressed = vec![0u8; data.len()];
+    let mut extents = Vec::with_capacity(data.len().div_ceil(chunk_size));
+
+    // 2. WARM-UP & PAGE-FAULTING: Ensure OS has actually allocated physical RAM
+    // This prevents "cold start" latency from ruining the first run.
+    prefault(&mut compressed);
+    prefault(&mut decompressed);
+
+    // Warm-up run, verified so that a codec producing garbage never gets a
+    // score. Memory is measured here only, keeping the tracker's heap trim and
+    // page-table reset out of the timed runs.
+    let tracker = Tracker::start();
+    compress_chunks(codec, data, chunk_size, &mut compressed, &mut extents)?;
+    tracker.finish(&mut results.compress_memory);
+    let tracker = Tracker::start();
+    let decompressed_len =
+        decompress_chunks(codec, &compressed, &extents, chunk_size, &mut decompressed)?;
+    tracker.finish(&mut results.decompress_memory);
+    verify(data, &decompressed[..decompressed_len])?;
+
+    results.input_sizes.reserve(num_runs);
+    results.compressed_sizes.reserve(num_runs);
+    results.compress_times.reserve(num_runs);
+    results.decompress_times.reserve(num_runs);
+
+    progress!(
+        "  [{}] Starting benchmark ({}, {} chunks)...",
+        name,
+        describe_runs(config),
+        extents.len()
+    );
+
+    let started = Instant::now();
+    while more_runs(config, results, started) {
+        let run = results.input_sizes.len();
+        // Compression
+        let cpu = CpuSnapshot::now();
+        start_counters(config);
+        let start = Instant::now();
+        let compressed_len =
+            compress_chunks(codec, data, chunk_size, &mut compressed, &mut extents)?;
+        let compress_time = start.elapsed();
+        stop_counters(config, &mut results.compress_perf);
+        cpu.finish(&mut results.compress_cpu);
+        let compressed_len = black_box(compressed_len);
+        results.compress_times.push(compress_time);
+        results.input_sizes.push(data.len());
+        results.compressed_sizes.push(compressed_len);
+        progress!(
+            "  [{}] Run {}: compressed to {} bytes in {:.3}ms",
+            name,
+            run + 1,
+            compressed_len,
+            compress_time.as_secs_f64() * 1000.0
+        );
+
+        // Decompression. Clear stale output first when verifying, so a codec
+        // that writes nothing cannot pass on the previous run's bytes.
+        if config.verify_each_run {
+            prefault(&mut decompressed);
+        }
+        let cpu = CpuSnapshot::now();
+        start_counters(config);
+        let start = Instant::now();
+        let decompressed_len =
+            decompress_chunks(codec, &compressed, &extents, chunk_size, &mut decompressed)?;
+        let decompress_time = start.elapsed();
+        stop_counters(config, &mut results.decompress_perf);
+        cpu.finish(&mut results.decompress_cpu);
+        results.decompress_times.push(decompress_time);
+        progress!(
+            "  [{}] Run {}: decompressed in {:.3}ms",
+            name,
+            run + 1,
+            decompress_time.as_secs_f64() * 1000.0
+        );
+        black_box(decompressed_len);
+
+        if config.verify_each_run {
+            verify(data, &decompressed[..decompressed_len])
+                .map_err(|e| format!("run {}: {}", run + 1, e))?;
+        }
+    }
+    results.runs_per_batch.push(results.input_sizes.len());
+
+    Ok(())
+}
+
+/// The run count for progress messages.
+fn describe_runs(config: &BenchConfig) -> String {
+    match &config.budget {
+        Some(budget) => format!(
+            "{:.1}s..{:.1}s, at least {} run{}",
+            budget.min_time.as_secs_f64(),
+            budget.max_time.as_secs_f64(),
+            config.num_runs,
+            if config.num_runs == 1 { "" } else { "s" }
+        ),
+        None => format!("{} runs", config.num_runs),
+    }
+}
+
+/// One producer/consumer pair of an `--interop` run: data compressed by
+/// `producer` and decompressed by `consumer`, totalled over all batches.
+#[derive(Serialize, Deserialize)]
+pub struct InteropPair {
+    pub format: String,
+    pub producer: String,
+    pub consumer: String,
+    pub input_size: usize,
+    pub compressed_size: usize,
+    /// Time `producer` took to compress the input, summed over `runs` passes.
+    pub compress_ns: u64,
+    /// Time `consumer` took to decompress it, summed over `runs` passes.
+    pub decompress_ns: u64,
+    pub runs: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
+}
+
+/// Compresses `data` with every codec of `group` and decompresses each
+/// result with every codec of the group, adding the measurements to the
+/// matching entries of `pairs`. A pair that fails is recorded and skipped on
+/// later batches.
+pub fn interop(
+    group: &mut InteropGroup,
+    data: &[u8],
+    config: &BenchConfig,
+    pairs: &mut Vec<InteropPair>,
+) {
+    let chunk_size = config.chunk_size;
+    let mut decompressed = vec![0u8; data.len()];
+    prefault(&mut decompressed);
+    let mut extents = Vec::with_capacity(data.len().div_ceil(chunk_size));
+
+    for producer in 0..group.codecs.len() {
+        let producer_name = group.codecs[producer].name().to_string();
+        let bound = data
+            .chunks(chunk_size)
+            .map(|chunk| group.codecs[producer].compress_bound(chunk.len()))
+            .sum();
+        let mut compressed = vec![0u8; bound];
+        prefault(&mut compressed);
+
+        // Warm up, then time as many passes as each consumer gets
+        let compressed_len = compress_chunks(
+            group.codecs[producer].as_mut(),
+            data,
+            chunk_size,
+            &mut compressed,
+            &mut extents,
+        )
+        .and_then(|len| {
+            let mut elapsed = Duration::ZERO;
+            for _ in 0..config.num_runs {
+                let start = Instant::now();
+                compress_chunks(
+                    group.codecs[producer].as_mut(),
+                    data,
+                    chunk_size,
+                    &mut compressed,
+                    &mut extents,
+                )?;
+                elapsed += start.elapsed();
+            }
+            Ok((len, elapsed))
+        });
+
+        for consumer in 0..group.codecs.len() {
+            let consumer_name = group.codecs[consumer].name().to_string();
+            let index = match pairs.iter().position(|p| {
+                p.format == group.format
+                    && p.producer == producer_name
+                    && p.consumer == consumer_name
+            }) {
+                Some(index) => index,
+                None => {
+                    pairs.push(InteropPair {
+                        format: group.format.to_string(),
+                        producer: producer_name.clone(),
+                        consumer: consumer_name.clone(),
+                        input_size: 0,
+                        compressed_size: 0,
+                        compress_ns: 0,
+                        decompress_ns: 0,
+                        runs: config.num_runs,
+                        failure: None,
+                    });
+                    pairs.len() - 1
+                }
+            };
+            let pair = &mut pairs[index];
+            if pair.failure.is_some() {
+                continue;
+            }
+            let (compressed_len, compress_time) = match &compressed_len {
+                Ok(result) => *result,
+                Err(e) => {
+                    pair.failure = Some(e.clone());
+                    continue;
+                }
+            };
+
+            let codec = group.codecs[consumer].as_mut();
+            let mut round_trip = |verify_output: bool| -> Result<Duration, String> {
+                decompressed.fill(0);
+                let start = Instant::now();
+                let len =
+                    decompress_chunks(codec, &compressed, &extents, chunk_size, &mut decompressed)?;
+                let elapsed = start.elapsed();
+                if verify_output {
+                    verify(data, &decompressed[..len])?;
+                }
+                Ok(elapsed)
+            };
+            let measured = round_trip(true).and_then(|_| {
+                (0..config.num_runs)
+                    .map(|_| round_trip(config.verify_each_run))
+                    .sum::<Result<Duration, String>>()
+            });
+            match measured {
+                Ok(decompress_time) => {
+                    progress!(
+                        "  [{} -> {}] decompressed {} runs in {:.3}ms",
+                        producer_name,
+                        consumer_name,
+                        config.num_runs,
+                        decompress_time.as_secs_f64() * 1000.0
+                    );
+                    pair.input_size += data.len();
+                    pair.compressed_size += compressed_len;
+                    pair.compress_ns += compress_time.as_nanos() as u64;
+                    pair.decompress_ns += decompress_time.as_nanos() as u64;
+                }
+                Err(e) => {
+                    progress!("  [{} -> {}] {}", producer_name, consumer_name, e);
+                    pair.failure = Some(e);
+                }
+            }
+        }
+    }
+}
+
+/// One decoder of the `decompress` subcommand, totalled over every input
+/// file of its format that it decoded.
+#[derive(Serialize, Deserialize)]
+pub struct DecodeMeasurement {
+    pub format: String,
+    pub decoder: String,
+    pub files: usize,
+    pub compressed_size: usize,
+    pub decompressed_size: usize,
+    /// Time to decompress every file once, per run.
+    pub decompress_ns: Vec<u64>,
+    /// `path: reason` for each file the decoder failed on; those files are
+    /// left out of the totals.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<String>,
+}
+
+/// Decompresses the pre-compressed `file` with `codec`: one untimed warm-up
+/// checked against `expected`, the reference decode, then `config.num_runs`
+/// timed passes into `output`, which must be `expected.len()` bytes. Each
+/// pass's time is added to the matching run of `measurement`; a failure is
+/// recorded there under `path` instead, and the file is left out.
+pub fn decode(
+    codec: &mut dyn Codec,
+    path: &str,
+    file: &[u8],
+    expected: &[u8],
+    output: &mut [u8],
+    config: &BenchConfig,
+    measurement: &mut DecodeMeasurement,
+) {
+    let mut pass = |verify_output: bool| -> Result<Duration, String> {
+        output.fill(0);
+        let start = Instant::now();
+        let len = codec
+            .decompress_into(black_box(file), output)
+            .map_err(|e| format!("decompression failed: {}", e))?;
+        let elapsed = start.elapsed();
+        if verify_output {
+            verify(expected, &output[..len])?;
+        }
+        Ok(elapsed)
+    };
+    let measured = pass(true).and_then(|_| {
+        (0..config.num_runs)
+            .map(|_| pass(config.verify_each_run))
+            .collect::<Result<Vec<Duration>, String>>()
+    });
+    match measured {
+        Ok(times) => {
+            progress!(
+                "  [{}] decompressed {} runs in {:.3}ms",
+                measurement.decoder,
+                config.num_runs,
+                times.iter().sum::<Duration>().as_secs_f64() * 1000.0
+            );
+            measurement.files += 1;
+            measurement.compressed_size += file.len();
+            measurement.decompressed_size += expected.len();
+            measurement.decompress_ns.resize(times.len(), 0);
+            for (total, time) in measurement.decompress_ns.iter_mut().zip(times) {
+                *total += time.as_nanos() as u64;
+            }
+        }
+        Err(e) => {
+            progress!("  [{}] FAILED: {}", measurement.decoder, e);
+            measurement.failures.push(format!("{}: {}", path, e));
+        }
+    }
+}
+
+/// What the workers of [`benchmark_parallel`] do between two barriers.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Allocate and pre-fault the buffers.
+    Prepare = 0,
+    Compress = 1,
+    Decompress = 2,
+    Verify = 3,
+    Stop = 4,
+}
+
+impl Phase {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Phase::Prepare,
+            1 => Phase::Compress,
+            2 => Phase::Decompress,
+            3 => Phase::Verify,
+            _ => Phase::Stop,
+        }
+    }
+}
+
+/// State shared between the coordinating thread and the workers.
+struct Shared {
+    /// Workers plus the coordinator; every phase starts and ends on it.
+    barrier: Barrier,
+    phase: AtomicU8,
+    compressed_total: AtomicUsize,
+    failure: Mutex<Option<String>>,
+}
+
+impl Shared {
+    fn fail(&self, failure: String) {
+        self.failure.lock().unwrap().get_or_insert(failure);
+    }
+
+    fn failed(&self) -> bool {
+        self.failure.lock().unwrap().is_some()
+    }
+
+    /// Runs one phase on every worker and returns its wall-clock duration.
+    fn run_phase(&self, phase: Phase) -> Duration {
+        self.phase.store(phase as u8, Ordering::SeqCst);
+        self.barrier.wait();
+        let start = Instant::now();
+        if phase != Phase::Stop {
+            self.barrier.wait();
+        }
+        start.elapsed()
+    }
+}
+
+/// One worker's codec instance, share of the input and private buffers.
+struct Worker<'a> {
+    codec: Box<dyn Codec>,
+    data: &'a [u8],
+    chunk_size: usize,
+    compressed: Vec<u8>,
+    decompressed: Vec<u8>,
+    decompressed_len: usize,
+    extents: Vec<Range<usize>>,
+}
+
+impl Worker<'_> {
+    fn run(&mut self, phase: Phase) -> Result<(), String> {
+        let (data, chunk_size) = (self.data, self.chunk_size);
+        match phase {
+            Phase::Prepare => {
+                let bound = data
+                    .chunks(chunk_size)
+                    .map(|chunk| self.codec.compress_bound(chunk.len()))
+                    .sum();
+                self.compressed = vec![0u8; bound];
+                self.decompressed = vec![0u8; data.len()];
+                self.extents = Vec::with_capacity(data.len().div_ceil(chunk_size));
+                prefault(&mut self.compressed);
+                prefault(&mut self.decompressed);
+                Ok(())
+            }
+            Phase::Compress => compress_chunks(
+                self.codec.as_mut(),
+                data,
+                chunk_size,
+                &mut self.compressed,
+                &mut self.extents,
+            )
+            .map(drop),
+            Phase::Decompress => {
+                self.decompressed_len = decompress_chunks(
+                    self.codec.as_mut(),
+                    &self.compressed,
+                    &self.extents,
+                    chunk_size,
+                    &mut self.decompressed,
+                )?;
+                Ok(())
+            }
+            Phase::Verify => {
+                let result = verify(data, &self.decompressed[..self.decompressed_len]);
+                // Clear the output so the next run cannot pass on stale bytes
+                prefault(&mut self.decompressed);
+                result
+            }
+            Phase::Stop => Ok(()),
+        }
+    }
+
+    fn compressed_len(&self) -> usize {
+        self.extents.last().map_or(0, |extent| extent.end)
+    }
+}
+
+/// Like [`benchmark`], but splits the chunks of `data` into `threads`
+/// contiguous shares compressed concurrently, each worker with its own codec
+/// instance from `spec`. Each run is timed on the wall clock from the moment
+/// all workers start until the last one finishes, so throughput is the
+/// aggregate across threads.
+pub fn benchmark_parallel(
+    spec: &CodecSpec,
+    data: &[u8],
+    config: &BenchConfig,
+    threads: usize,
+) -> BenchmarkResults {
+    let name = format!("{} x{}", spec.name(), threads);
+    let chunk_size = config.chunk_size;
+    let num_chunks = data.len().div_ceil(chunk_size);
+    if num_chunks < threads {
+        progress!(
+            "  [{}] Only {} chunks for {} threads; some workers will idle",
+            name,
+            num_chunks,
+            threads
+        );
+    }
+
+    let shared = Shared {
+        barrier: Barrier::new(threads + 1),
+        phase: AtomicU8::new(Phase::Stop as u8),
+        compressed_total: AtomicUsize::new(0),
+        failure: Mutex::new(None),
+    };
+    let mut results = BenchmarkResults::new();
+
+    thread::scope(|scope| {
+        for worker_index in 0..threads {
+            let first = num_chunks * worker_index / threads;
+            let last = num_chunks * (worker_index + 1) / threads;
+            let share =
+                &data[(first * chunk_size).min(data.len())..(last * chunk_size).min(data.len())];
+            let shared = &shared;
+            scope.spawn(move || {
+                let mut worker = Worker {
+                    codec: spec.build(),
+                    data: share,
+                    chunk_size,
+                    compressed: Vec::new(),
+                    decompressed: Vec::new(),
+                    decompressed_len: 0,
+                    extents: Vec::new(),
+                };
+                loop {
+                    shared.barrier.wait();
+                    let phase = Phase::from_u8(shared.phase.load(Ordering::SeqCst));
+                    if phase == Phase::Stop {
+                        break;
+                    }
+                    // After a failure elsewhere, keep meeting the barriers but
+                    // skip the work.
+                    if !shared.failed() {
+                        match worker.run(phase) {
+                            Ok(()) if phase == Phase::Compress => {
+                                shared
+                                    .compressed_total
+                                    .fetch_add(worker.compressed_len(), Ordering::SeqCst);
+                            }
+                            Ok(()) => {}
+                            Err(e) => shared.fail(e),
+                        }
+                    }
+                    shared.barrier.wait();
+                }
+            });
+        }
+
+        progress!("  [{}] Preparing buffers ...", name);
+        shared.run_phase(Phase::Prepare);
+        // Verified warm-up, so that a codec producing garbage never gets a
+        // score, and the only pass memory is measured over
+        let tracker = Tracker::start();
+        shared.run_phase(Phase::Compress);
+        tracker.finish(&mut results.compress_memory);
+        let tracker = Tracker::start();
+        shared.run_phase(Phase::Decompress);
+        tracker.finish(&mut results.decompress_memory);
+        shared.run_phase(Phase::Verify);
+        if !shared.failed() {
+            progress!(
+                "  [{}] Starting benchmark ({}, {} chunks)...",
+                name,
+                describe_runs(config),
+                num_chunks
+            );
+        }
+
+        let started = Instant::now();
+        while more_runs(config, &mut results, started) {
+            if shared.failed() {
+                break;
+            }
+            let run = results.input_sizes.len();
+            shared.compressed_total.store(0, Ordering::SeqCst);
+            let cpu = CpuSnapshot::now();
+            start_counters(config);
+            let compress_time = shared.run_phase(Phase::Compress);
+            stop_counters(config, &mut results.compress_perf);
+            cpu.finish(&mut results.compress_cpu);
+            let cpu = CpuSnapshot::now();
+            start_counters(config);
+            let decompress_time = shared.run_phase(Phase::Decompress);
+            stop_counters(config, &mut results.decompress_perf);
+            cpu.finish(&mut results.decompress_cpu);
+            if config.verify_each_run {
+                shared.run_phase(Phase::Verify);
+            }
+            if shared.failed() {
+                break;
+            }
+
+            let compressed_len = shared.compressed_total.load(Ordering::SeqCst);
+            results.input_sizes.push(data.len());
+            results.compressed_sizes.push(compressed_len);
+            results.compress_times.push(compress_time);
+            results.decompress_times.push(decompress_time);
+            progress!(
+                "  [{}] Run {}: compressed to {} bytes in {:.3}ms, decompressed in {:.3}ms",
+                name,
+                run + 1,
+                compressed_len,
+                compress_time.as_secs_f64() * 1000.0,
+                decompress_time.as_secs_f64() * 1000.0
+            );
+        }
+
+        shared.run_phase(Phase::Stop);
+    });
+
+    match shared.failure.into_inner().unwrap() {
+        Some(failure) => {
+            progress!("  [{}] FAILED: {}", name, failure);
+            results.failure = Some(failure);
+        }
+        None => results.runs_per_batch.push(results.input_sizes.len()),
+    }
+    results
+}
@@ -0,0 +1,46 @@
+//! Metadata about the machine and build that produced a set of results.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct HostInfo {
+    pub cpu_model: String,
+    pub cpu_count: usize,
+    pub kernel: String,
+    pub rustc: String,
+    /// Resolved versions of the direct dependencies, from Cargo.lock.
+    pub crates: BTreeMap<String, String>,
+}
+
+impl HostInfo {
+    pub fn collect() -> Self {
+        let crates = env!("BENCH_CRATE_VERSIONS")
+            .split(',')
+            .filter_map(|entry| entry.split_once('='))
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect();
+
+        Self {
+            cpu_model: cpu_model().unwrap_or_else(|| "unknown".to_string()),
+            cpu_count: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            kernel: fs::read_to_string("/proc/sys/kernel/osrelease")
+                .map(|s| s.trim().to_string())
+                .unwrap_or_else(|_| "unknown".to_string()),
+            rustc: env!("BENCH_RUSTC_VERSION").to_string(),
+            crates,
+        }
+    }
+}
+
+/// First `model name` entry in /proc/cpuinfo.
+fn cpu_model() -> Option<String> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "model name")
+        .map(|(_, value)| value.trim().to_string())
+}
@@ -0,0 +1,91 @@
+//! What the benchmark reads: files given on the command line, with
+//! directories walked recursively, or a `--synthetic` generator.
+
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::path::Path;
+
+use crate::synthetic::{Spec, Synthetic};
+
+/// An opened input. Seekable so that dictionary training can sample it
+/// before the benchmark reads it.
+pub trait Input: Read + Seek {}
+
+impl<T: Read + Seek> Input for T {}
+
+enum Kind {
+    File,
+    Synthetic { spec: Spec, seed: u64 },
+}
+
+/// One input to benchmark, named as the report shows it.
+pub struct Source {
+    pub name: String,
+    pub size: usize,
+    kind: Kind,
+}
+
+impl Source {
+    pub fn synthetic(spec: &Spec, seed: u64) -> Self {
+        Self {
+            name: spec.label(seed),
+            size: spec.size,
+            kind: Kind::Synthetic { spec: *spec, seed },
+        }
+    }
+
+    pub fn open(&self) -> io::Result<Box<dyn Input>> {
+        match &self.kind {
+            Kind::File => fs::File::open(&self.name)
+                .map(|file| Box::new(file) as Box<dyn Input>)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", self.name, e))),
+            Kind::Synthetic { spec, seed } => Ok(Box::new(Synthetic::new(spec, *seed))),
+        }
+    }
+}
+
+/// Every file under `paths`, directories walked recursively in name order
+/// and empty files left out. Symlinks to files are followed, symlinks to
+/// directories are not, so a link cycle cannot loop the walk.
+pub fn collect(paths: &[String]) -> io::Result<Vec<Source>> {
+    let mut sources = Vec::new();
+    for path in paths {
+        let metadata =
+            fs::metadata(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+        if metadata.is_dir() {
+            walk(Path::new(path), &mut sources)?;
+        } else if metadata.len() > 0 {
+            sources.push(Source {
+                name: path.clone(),
+                size: metadata.len() as usize,
+                kind: Kind::File,
+            });
+        }
+    }
+    Ok(sources)
+}
+
+fn walk(dir: &Path, sources: &mut Vec<Source>) -> io::Result<()> {
+    let context = |e: io::Error| io::Error::new(e.kind(), format!("{}: {}", dir.display(), e));
+    let mut entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+        .map_err(context)?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type().map_err(context)?.is_dir() {
+            walk(&path, sources)?;
+            continue;
+        }
+        // Follows symlinks; dangling ones and special files are skipped
+        match fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() && metadata.len() > 0 => sources.push(Source {
+                name: path.display().to_string(),
+                size: metadata.len() as usize,
+                kind: Kind::File,
+            }),
+            _ => {}
+        }
+    }
+    Ok(())
+}
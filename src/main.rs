@@ -1,913 +1,603 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set when stdout carries a machine-readable report, so that progress chatter
+/// does not corrupt it.
+static PROGRESS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// `println!` for progress output; goes to stderr while stdout is reserved for
+/// a JSON or CSV report.
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        if $crate::PROGRESS_TO_STDERR.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+mod baseline;
+mod cli;
+mod codecs;
+mod cost;
+mod cpu;
+mod decompress;
+mod dictionary;
+mod harness;
+mod host;
+mod input;
+mod memory;
+mod pareto;
+mod perf;
+mod report;
+mod rng;
+mod select;
+mod stability;
+mod stats;
+mod synthetic;
+
 use std::env;
 use std::fs;
-use std::hint::black_box;
-use std::io::{Read, Write};
-use std::time::Instant;
+use std::io::{self, Read};
+use std::sync::Arc;
 use std::time::Duration;
 
-const CHUNK_MB: usize = 256;
-const CHUNK_SIZE: usize = CHUNK_MB * 1024 * 1024; // 256 MB
-
-struct BenchmarkResults {
-    input_sizes: Vec<usize>,
-    compressed_sizes: Vec<usize>,
-    compress_times: Vec<Duration>,
-    decompress_times: Vec<Duration>,
+use codecs::{Codec, CodecSpec, Mode, RegistryConfig};
+use harness::{BenchConfig, BenchmarkResults};
+use host::HostInfo;
+use input::Source;
+use report::{DictionaryTraining, FileResults, Format, Measurement, Report, ZeroBlockSkip};
+use stability::Stability;
+
+#[global_allocator]
+static ALLOCATOR: memory::CountingAllocator = memory::CountingAllocator;
+
+/// How much of the input is read and handed to the harness at once. Rounded
+/// down to a whole number of chunks, so chunks never straddle two batches.
+const READ_BATCH_SIZE: usize = 256 * 1024 * 1024; // 256 MB
+
+/// One row of the report: a codec at one thread count. Single-threaded rows
+/// keep one codec instance across batches; parallel rows build one per worker.
+struct Entry {
+    spec: CodecSpec,
+    threads: usize,
+    codec: Option<Box<dyn Codec>>,
+    results: BenchmarkResults,
+    /// Results on the file being read, merged into `results` after it.
+    file: BenchmarkResults,
 }
 
-impl BenchmarkResults {
-    fn new() -> Self {
-        Self {
-            input_sizes: Vec::new(),
-            compressed_sizes: Vec::new(),
-            compress_times: Vec::new(),
-            decompress_times: Vec::new(),
-        }
-    }
-
-    fn merge(&mut self, other: BenchmarkResults) {
-        self.input_sizes.extend(other.input_sizes);
-        self.compressed_sizes.extend(other.compressed_sizes);
-        self.compress_times.extend(other.compress_times);
-        self.decompress_times.extend(other.decompress_times);
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
     }
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    
-    // Parse arguments
-    let mut filename = None;
-    let mut num_runs = 1; // Default to 1 run
-    
-    let mut arg_index = 1;
-    while arg_index < args.len() {
-        match args[arg_index].as_str() {
-            "--runs" => {
-                if arg_index + 1 >= args.len() {
-                    eprintln!("Error: --runs requires a value");
-                    eprintln!("Usage: {} [--runs <N>] <filename>", args[0]);
-                    std::process::exit(1);
-                }
-                num_runs = args[arg_index + 1].parse().unwrap_or_else(|_| {
-                    eprintln!("Error: --runs value must be a number");
-                    std::process::exit(1);
-                });
-                if num_runs == 0 {
-                    eprintln!("Error: --runs value must be at least 1");
-                    std::process::exit(1);
-                }
-                arg_index += 2;
-            }
-            arg if !arg.starts_with("--") => {
-                if filename.is_some() {
-                    eprintln!("Error: multiple filenames provided");
-                    eprintln!("Usage: {} [--runs <N>] <filename>", args[0]);
-                    std::process::exit(1);
-                }
-                filename = Some(arg.to_string());
-                arg_index += 1;
-            }
-            _ => {
-                eprintln!("Error: unknown option '{}'", args[arg_index]);
-                eprintln!("Usage: {} [--runs <N>] <filename>", args[0]);
-                std::process::exit(1);
-            }
-        }
-    }
-    
-    let filename = filename.unwrap_or_else(|| {
-        eprintln!("Usage: {} [--runs <N>] <filename>", args[0]);
-        std::process::exit(1);
-    });
-
-    // Get file size
-    let metadata = fs::metadata(&filename).expect("Failed to read file metadata");
-    let original_size = metadata.len() as usize;
-
-    println!("File: {}", filename);
-    println!(
-        "Original size: {} bytes ({:.2} MiB)",
-        original_size,
-        original_size as f64 / (1024.0 * 1024.0)
-    );
-    println!("Number of runs per algorithm: {}", num_runs);
-    println!();
-
-    // Process file in chunks
-    let mut file = fs::File::open(&filename).expect("Failed to open file");
-    let mut chunk_number = 0;
-
-    let mut flate2_results = BenchmarkResults::new();
-    let mut snap_results = BenchmarkResults::new();
-    let mut lz4_results = BenchmarkResults::new();
-    let mut zstd_fastest_results = BenchmarkResults::new();
-    let mut zstd_balanced_results = BenchmarkResults::new();
-    let mut zstd_max_results = BenchmarkResults::new();
-    let mut xz2_results = BenchmarkResults::new();
-    let mut lzma_rs_results = BenchmarkResults::new();
-    let mut miniz_oxide_results = BenchmarkResults::new();
-    let mut lz4_flex_results = BenchmarkResults::new();
-    let mut libdeflate_results = BenchmarkResults::new();
-    let mut memcpy_results = BenchmarkResults::new();
-
-    loop {
-        let mut chunk = vec![0u8; CHUNK_SIZE];
-        let bytes_read = file.read(&mut chunk).expect("Failed to read chunk");
-
-        if bytes_read == 0 {
-            break;
+/// Drops every all-zero `block_size` block from `batch`, compacting the rest
+/// in place, and tallies what was dropped into `skip`.
+fn skip_zero_blocks(batch: &mut Vec<u8>, block_size: usize, skip: &mut ZeroBlockSkip) {
+    let mut kept = 0;
+    let mut offset = 0;
+    while offset < batch.len() {
+        let end = (offset + block_size).min(batch.len());
+        skip.blocks_read += 1;
+        skip.bytes_read += end - offset;
+        if batch[offset..end].iter().all(|&b| b == 0) {
+            skip.blocks_skipped += 1;
+            skip.bytes_skipped += end - offset;
+        } else {
+            batch.copy_within(offset..end, kept);
+            kept += end - offset;
         }
-
-        chunk.truncate(bytes_read);
-        chunk_number += 1;
-
-        println!("Processing chunk {} ({} bytes)...", chunk_number, bytes_read);
-
-        // Benchmark each compression algorithm on this chunk
-        memcpy_results.merge(benchmark_memcpy(&chunk, num_runs));
-        flate2_results.merge(benchmark_flate2(&chunk, bytes_read, num_runs));
-        snap_results.merge(benchmark_snap(&chunk, bytes_read, num_runs));
-        lz4_results.merge(benchmark_lz4(&chunk, bytes_read, num_runs));
-        zstd_fastest_results.merge(benchmark_zstd_fastest(&chunk, bytes_read, num_runs));
-        zstd_balanced_results.merge(benchmark_zstd_balanced(&chunk, bytes_read, num_runs));
-        zstd_max_results.merge(benchmark_zstd_max(&chunk, bytes_read, num_runs));
-        xz2_results.merge(benchmark_xz2(&chunk, bytes_read, num_runs));
-        lzma_rs_results.merge(benchmark_lzma_rs(&chunk, bytes_read, num_runs));
-        miniz_oxide_results.merge(benchmark_miniz_oxide(&chunk, bytes_read, num_runs));
-        lz4_flex_results.merge(benchmark_lz4_flex(&chunk, bytes_read, num_runs));
-        libdeflate_results.merge(benchmark_libdeflate(&chunk, bytes_read, num_runs));
+        offset = end;
     }
-
-    println!();
-    println!(
-        "| {:<20} | {:>6} | {:>16} | {:>18} |",
-        "Algorithm",
-        "Ratio",
-        "Compress (MiB/s)",
-        "Decompress (MiB/s)"
-    );
-    println!("| {:-<20} | {:-<6} | {:-<16} | {:-<18} |", "", "", "", "");
-
-    // Print aggregated results
-    print_results("memcpy", &memcpy_results);
-    print_results("flate2 (gzip)", &flate2_results);
-    print_results("snap (snappy)", &snap_results);
-    print_results("lz4", &lz4_results);
-    print_results("zstd (level 1)", &zstd_fastest_results);
-    print_results("zstd (level 3)", &zstd_balanced_results);
-    print_results("zstd (level 10)", &zstd_max_results);
-    print_results("xz2 (lzma)", &xz2_results);
-    print_results("lzma-rs", &lzma_rs_results);
-    print_results("miniz_oxide", &miniz_oxide_results);
-    print_results("lz4_flex", &lz4_flex_results);
-    print_results("libdeflate", &libdeflate_results);
+    batch.truncate(kept);
 }
 
-fn benchmark_memcpy(data: &[u8], num_runs: usize) -> BenchmarkResults {
-    println!("  [memcpy] Preparing buffers ...");
-
-    // 1. Pre-allocate buffers
-    let len = data.len();
-    let mut compressed = vec![0u8; len];
-    let mut decompressed = vec![0u8; len];
-
-    // 2. WARM-UP & PAGE-FAULTING: Ensure OS has actually allocated physical RAM
-    // This prevents "cold start" latency from ruining the first run.
-    for byte in &mut compressed {
-        *byte = 0;
-    }
-    for byte in &mut decompressed {
-        *byte = 0;
-    }
-
-    // Warm-up run
-    unsafe {
-        std::ptr::copy_nonoverlapping(black_box(data.as_ptr()), compressed.as_mut_ptr(), len);
-        std::ptr::copy_nonoverlapping(compressed.as_ptr(), decompressed.as_mut_ptr(), len);
-    }
-    black_box(&mut compressed);
-    black_box(&mut decompressed);
-
-    let mut input_sizes = Vec::with_capacity(num_runs);
-    let mut compressed_sizes = Vec::with_capacity(num_runs);
-    let mut compress_times = Vec::with_capacity(num_runs);
-    let mut decompress_times = Vec::with_capacity(num_runs);
-
-    println!("  [memcpy] Starting benchmark ({} runs)...", num_runs);
-
-    for run in 0..num_runs {
-        // --- Forward Copy (Data -> Compressed) ---
-        let src = black_box(data.as_ptr());
-        let dst = black_box(compressed.as_mut_ptr());
-
-        let start = Instant::now();
-        unsafe {
-            // This is the closest Rust equivalent to C's memcpy
-            std::ptr::copy_nonoverlapping(src, dst, len);
-        }
-        // Force the CPU to treat the memory as 'dirty' so the copy isn't skipped
-        black_box(&mut compressed);
-        let compress_time = start.elapsed();
-
-        // --- Backward Copy (Compressed -> Decompressed) ---
-        let src_back = black_box(compressed.as_ptr());
-        let dst_back = black_box(decompressed.as_mut_ptr());
-
-        let start_back = Instant::now();
-        unsafe {
-            std::ptr::copy_nonoverlapping(src_back, dst_back, len);
+/// Reads until `buf` is full or the reader hits EOF, returning the bytes read.
+/// A single `read` may return less than asked even mid-file, which would
+/// misalign the chunks that follow.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
         }
-        black_box(&mut decompressed);
-        let decompress_time = start_back.elapsed();
-
-        // Calculate and Print MiB/s for immediate feedback
-        let mib_s = (len as f64 / (1024.0 * 1024.0)) / compress_time.as_secs_f64();
-        println!(
-            "  Run {}: {:.2} MiB/s ({:.3}ms)",
-            run + 1,
-            mib_s,
-            compress_time.as_secs_f64() * 1000.0
-        );
-
-        compress_times.push(compress_time);
-        decompress_times.push(decompress_time);
-        input_sizes.push(len);
-        compressed_sizes.push(black_box(len));
-    }
-
-    BenchmarkResults {
-        input_sizes,
-        compressed_sizes,
-        compress_times,
-        decompress_times,
     }
+    Ok(filled)
 }
 
-fn benchmark_flate2(data: &[u8], _original_size: usize, num_runs: usize) -> BenchmarkResults {
-    use flate2::write::{GzDecoder, GzEncoder};
-    use flate2::Compression;
-
-    println!("  [flate2] Starting benchmark...");
-    let mut input_sizes = Vec::with_capacity(num_runs);
-    let mut compressed_sizes = Vec::with_capacity(num_runs);
-    let mut compress_times = Vec::with_capacity(num_runs);
-    let mut decompress_times = Vec::with_capacity(num_runs);
-
-    let mut compressed = vec![0u8; data.len() + 1024];
-    let mut decompressed = vec![0u8; data.len()];
-
-    for byte in &mut compressed {
-        *byte = 0;
+/// The inputs named on the command line, exiting if there is nothing to
+/// read.
+fn sources(options: &cli::Options) -> Vec<Source> {
+    if let Some(spec) = &options.synthetic {
+        return vec![Source::synthetic(spec, options.seed)];
     }
-    for byte in &mut decompressed {
-        *byte = 0;
-    }
-    compressed.clear();
-    decompressed.clear();
-
-    // Warm-up run
-    let mut encoder = GzEncoder::new(compressed, Compression::default());
-    encoder.write_all(black_box(data)).unwrap();
-    compressed = encoder.finish().unwrap();
-    let mut decoder = GzDecoder::new(decompressed);
-    decoder.write_all(black_box(&compressed)).unwrap();
-    decompressed = decoder.finish().unwrap();
-    black_box(compressed.len());
-    black_box(decompressed.len());
-
-    for run in 0..num_runs {
-        // Compression
-        compressed.clear();
-        let start = Instant::now();
-        let mut encoder = GzEncoder::new(compressed, Compression::default());
-        encoder.write_all(black_box(data)).unwrap();
-        compressed = encoder.finish().unwrap();
-        let compress_time = start.elapsed();
-        let compressed_len = black_box(compressed.len());
-        compress_times.push(compress_time);
-        input_sizes.push(data.len());
-        compressed_sizes.push(compressed_len);
-        println!("  [flate2] Run {}: compressed to {} bytes in {:.3}ms", 
-                 run + 1, compressed_len, compress_time.as_secs_f64() * 1000.0);
-
-        // Decompression
-        decompressed.clear();
-        let start = Instant::now();
-        let mut decoder = GzDecoder::new(decompressed);
-        decoder.write_all(black_box(&compressed)).unwrap();
-        decompressed = decoder.finish().unwrap();
-        let decompress_time = start.elapsed();
-        let decompressed_len = black_box(decompressed.len());
-        decompress_times.push(decompress_time);
-        println!("  [flate2] Run {}: decompressed in {:.3}ms", 
-                 run + 1, decompress_time.as_secs_f64() * 1000.0);
-        black_box(decompressed_len);
-    }
-
-    BenchmarkResults {
-        input_sizes,
-        compressed_sizes,
-        compress_times,
-        decompress_times,
-    }
-}
-
-fn benchmark_snap(data: &[u8], _original_size: usize, num_runs: usize) -> BenchmarkResults {
-    use snap::raw::{Decoder, Encoder};
-    use snap::raw::max_compress_len;
-
-    println!("  [snap] Starting benchmark...");
-    let mut input_sizes = Vec::with_capacity(num_runs);
-    let mut compressed_sizes = Vec::with_capacity(num_runs);
-    let mut compress_times = Vec::with_capacity(num_runs);
-    let mut decompress_times = Vec::with_capacity(num_runs);
-
-    let max_len = max_compress_len(data.len());
-    let mut compressed = vec![0u8; max_len];
-    let mut decompressed = vec![0u8; data.len()];
-
-    for byte in &mut compressed {
-        *byte = 0;
-    }
-    for byte in &mut decompressed {
-        *byte = 0;
-    }
-
-    let mut encoder = Encoder::new();
-    let mut decoder = Decoder::new();
-
-    // Warm-up run
-    let compressed_len = encoder.compress(black_box(data), &mut compressed).unwrap();
-    let decompressed_len = decoder
-        .decompress(black_box(&compressed[..compressed_len]), &mut decompressed)
-        .unwrap();
-    black_box(compressed_len);
-    black_box(decompressed_len);
-
-    for run in 0..num_runs {
-        // Compression
-        let start = Instant::now();
-        let compressed_len = encoder.compress(black_box(data), &mut compressed).unwrap();
-        let compress_time = start.elapsed();
-        let compressed_len = black_box(compressed_len);
-        compress_times.push(compress_time);
-        input_sizes.push(data.len());
-        compressed_sizes.push(compressed_len);
-        println!("  [snap] Run {}: compressed to {} bytes in {:.3}ms", 
-                 run + 1, compressed_len, compress_time.as_secs_f64() * 1000.0);
-
-        // Decompression
-        let start = Instant::now();
-        let decompressed_len = decoder
-            .decompress(black_box(&compressed[..compressed_len]), &mut decompressed)
-            .unwrap();
-        let decompress_time = start.elapsed();
-        decompress_times.push(decompress_time);
-        println!("  [snap] Run {}: decompressed in {:.3}ms", 
-                 run + 1, decompress_time.as_secs_f64() * 1000.0);
-        black_box(decompressed_len);
-    }
-
-    BenchmarkResults {
-        input_sizes,
-        compressed_sizes,
-        compress_times,
-        decompress_times,
+    let sources = input::collect(&options.paths).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    if sources.is_empty() {
+        eprintln!("Error: no non-empty files to benchmark");
+        std::process::exit(1);
     }
+    sources
 }
 
-fn benchmark_lz4(data: &[u8], original_size: usize, num_runs: usize) -> BenchmarkResults {
-    use lz4::block::{compress_bound, compress_to_buffer, decompress_to_buffer};
-
-    println!("  [lz4] Starting benchmark...");
-    let mut input_sizes = Vec::with_capacity(num_runs);
-    let mut compressed_sizes = Vec::with_capacity(num_runs);
-    let mut compress_times = Vec::with_capacity(num_runs);
-    let mut decompress_times = Vec::with_capacity(num_runs);
-
-    let max_len = compress_bound(data.len()).unwrap_or(data.len());
-    let mut compressed = vec![0u8; max_len];
-    let mut decompressed = vec![0u8; original_size];
+fn main() {
+    let args: Vec<String> = env::args().collect();
 
-    for byte in &mut compressed {
-        *byte = 0;
-    }
-    for byte in &mut decompressed {
-        *byte = 0;
-    }
+    let options = cli::parse_args(&args);
 
-    // Warm-up run
-    let compressed_len =
-        compress_to_buffer(black_box(data), None, false, &mut compressed).unwrap();
-    let decompressed_len = decompress_to_buffer(
-        black_box(&compressed[..compressed_len]),
-        Some(original_size as i32),
-        &mut decompressed,
-    )
-    .unwrap();
-    black_box(compressed_len);
-    black_box(decompressed_len);
-
-    for run in 0..num_runs {
-        // Compression
-        let start = Instant::now();
-        let compressed_len =
-            compress_to_buffer(black_box(data), None, false, &mut compressed).unwrap();
-        let compress_time = start.elapsed();
-        let compressed_len = black_box(compressed_len);
-        compress_times.push(compress_time);
-        input_sizes.push(data.len());
-        compressed_sizes.push(compressed_len);
-        println!("  [lz4] Run {}: compressed to {} bytes in {:.3}ms", 
-                 run + 1, compressed_len, compress_time.as_secs_f64() * 1000.0);
-
-        // Decompression
-        let start = Instant::now();
-        let decompressed_len = decompress_to_buffer(
-            black_box(&compressed[..compressed_len]),
-            Some(original_size as i32),
-            &mut decompressed,
-        )
-        .unwrap();
-        let decompress_time = start.elapsed();
-        decompress_times.push(decompress_time);
-        println!("  [lz4] Run {}: decompressed in {:.3}ms", 
-                 run + 1, decompress_time.as_secs_f64() * 1000.0);
-        black_box(decompressed_len);
+    if options.format != Format::Markdown && options.output.is_none() {
+        PROGRESS_TO_STDERR.store(true, Ordering::Relaxed);
     }
 
-    BenchmarkResults {
-        input_sizes,
-        compressed_sizes,
-        compress_times,
-        decompress_times,
+    if let Some(files) = &options.decompress {
+        decompress::run(files, &options);
+        return;
     }
-}
-
-fn benchmark_zstd(data: &[u8], original_size: usize, num_runs: usize, level: i32, level_name: &str) -> BenchmarkResults {
-    println!("  [zstd {}] Starting benchmark...", level_name);
-    let mut input_sizes = Vec::with_capacity(num_runs);
-    let mut compressed_sizes = Vec::with_capacity(num_runs);
-    let mut compress_times = Vec::with_capacity(num_runs);
-    let mut decompress_times = Vec::with_capacity(num_runs);
 
-    let max_len = zstd::zstd_safe::compress_bound(data.len());
-    let mut compressed = vec![0u8; max_len];
-    let mut decompressed = vec![0u8; original_size];
+    // Listing only needs the labels, so it opens no input and an empty
+    // dictionary stands in for one trained on it
+    let sources = if options.list_algorithms {
+        Vec::new()
+    } else {
+        sources(&options)
+    };
+    let mut dictionary_training: Option<DictionaryTraining> = None;
+    let zstd_dictionary = options.zstd_dict.map(|max_size| {
+        if sources.is_empty() {
+            return Arc::new(Vec::new());
+        }
+        progress!("Training zstd dictionary ...");
+        let (dictionary, training) =
+            dictionary::train(&sources, options.chunk_size, max_size, options.dict_sample)
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: failed to train zstd dictionary: {}", e);
+                    std::process::exit(1);
+                });
+        dictionary_training = Some(training);
+        Arc::new(dictionary)
+    });
 
-    for byte in &mut compressed {
-        *byte = 0;
-    }
-    for byte in &mut decompressed {
-        *byte = 0;
-    }
+    let registry = codecs::registry(&RegistryConfig {
+        levels: options.levels.clone(),
+        containers: options.containers.clone(),
+        modes: options.modes.clone(),
+        io_buffer: options.io_buffer,
+        zstd_dictionary,
+        zstd_streams: options.zstd_streams.clone(),
+    })
+    .unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
 
-    // Warm-up run
-    let compressed_len = zstd::bulk::compress_to_buffer(black_box(data), &mut compressed, level)
-        .unwrap();
-    let decompressed_len =
-        zstd::bulk::decompress_to_buffer(black_box(&compressed[..compressed_len]), &mut decompressed)
-            .unwrap();
-    black_box(compressed_len);
-    black_box(decompressed_len);
-
-    for run in 0..num_runs {
-        // Compression
-        let start = Instant::now();
-        let compressed_len =
-            zstd::bulk::compress_to_buffer(black_box(data), &mut compressed, level).unwrap();
-        let compress_time = start.elapsed();
-        let compressed_len = black_box(compressed_len);
-        compress_times.push(compress_time);
-        input_sizes.push(data.len());
-        compressed_sizes.push(compressed_len);
-        println!("  [zstd {}] Run {}: compressed to {} bytes in {:.3}ms", 
-                 level_name, run + 1, compressed_len, compress_time.as_secs_f64() * 1000.0);
-
-        // Decompression
-        let start = Instant::now();
-        let decompressed_len = zstd::bulk::decompress_to_buffer(
-            black_box(&compressed[..compressed_len]),
-            &mut decompressed,
-        )
-        .unwrap();
-        let decompress_time = start.elapsed();
-        decompress_times.push(decompress_time);
-        println!("  [zstd {}] Run {}: decompressed in {:.3}ms", 
-                 level_name, run + 1, decompress_time.as_secs_f64() * 1000.0);
-        black_box(decompressed_len);
+    if options.list_algorithms {
+        for codec in &registry {
+            println!("{}", codec.name());
+        }
+        return;
     }
 
-    BenchmarkResults {
-        input_sizes,
-        compressed_sizes,
-        compress_times,
-        decompress_times,
+    let codecs =
+        select::filter(registry, &options.algorithms, &options.exclude).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+    if codecs.is_empty() {
+        eprintln!("Error: no algorithms left to run after --algorithms/--exclude");
+        std::process::exit(1);
     }
-}
-
-fn benchmark_zstd_fastest(data: &[u8], original_size: usize, num_runs: usize) -> BenchmarkResults {
-    benchmark_zstd(data, original_size, num_runs, 1, "fastest")
-}
-
-fn benchmark_zstd_balanced(data: &[u8], original_size: usize, num_runs: usize) -> BenchmarkResults {
-    benchmark_zstd(data, original_size, num_runs, 3, "balanced")
-}
-
-fn benchmark_zstd_max(data: &[u8], original_size: usize, num_runs: usize) -> BenchmarkResults {
-    benchmark_zstd(data, original_size, num_runs, 10, "max")
-}
 
-fn benchmark_xz2(data: &[u8], _original_size: usize, num_runs: usize) -> BenchmarkResults {
-    use xz2::read::{XzDecoder, XzEncoder};
+    // Load the baseline up front, so that a typo fails before the benchmark
+    let baseline = options.compare.as_deref().map(|name| {
+        baseline::load(name).unwrap_or_else(|e| {
+            eprintln!("Error: failed to load baseline: {}", e);
+            std::process::exit(1);
+        })
+    });
 
-    println!("  [xz2] Starting benchmark...");
-    let mut input_sizes = Vec::with_capacity(num_runs);
-    let mut compressed_sizes = Vec::with_capacity(num_runs);
-    let mut compress_times = Vec::with_capacity(num_runs);
-    let mut decompress_times = Vec::with_capacity(num_runs);
+    let corpus = sources.len() > 1;
+    let input_name = if corpus {
+        options.paths.join(", ")
+    } else {
+        sources[0].name.clone()
+    };
+    let original_size: usize = sources.iter().map(|source| source.size).sum();
+    let num_runs = options.num_runs;
+    let chunk_size = options.chunk_size;
+    let config = BenchConfig {
+        num_runs,
+        budget: options.budget,
+        chunk_size,
+        verify_each_run: options.verify_each_run,
+        counters: perf::Counters::open(),
+    };
+    // Batches hold whole chunks and, when skipping, whole zero-check blocks
+    let unit = match options.skip_zero_blocks {
+        Some(block_size) => chunk_size / gcd(chunk_size, block_size) * block_size,
+        None => chunk_size,
+    };
+    let batch_size = (READ_BATCH_SIZE / unit).max(1) * unit;
+    let mut zero_blocks = options.skip_zero_blocks.map(ZeroBlockSkip::new);
 
-    let mut compressed = vec![0u8; data.len() + 1024 * 1024];
-    let mut decompressed = vec![0u8; data.len()];
-    for byte in &mut compressed {
-        *byte = 0;
-    }
-    for byte in &mut decompressed {
-        *byte = 0;
+    progress!("Input: {}", input_name);
+    if corpus {
+        progress!("Files: {}", sources.len());
     }
-    compressed.clear();
-    decompressed.clear();
-
-    // Warm-up run
-    let mut encoder = XzEncoder::new(black_box(&data[..]), 6);
-    encoder.read_to_end(&mut compressed).unwrap();
-    let mut decoder = XzDecoder::new(black_box(&compressed[..]));
-    decoder.read_to_end(&mut decompressed).unwrap();
-    black_box(compressed.len());
-    black_box(decompressed.len());
-
-    for run in 0..num_runs {
-        // Compression
-        compressed.clear();
-        let start = Instant::now();
-        let mut encoder = XzEncoder::new(black_box(&data[..]), 6);
-        encoder.read_to_end(&mut compressed).unwrap();
-        let compress_time = start.elapsed();
-        let compressed_len = black_box(compressed.len());
-        compress_times.push(compress_time);
-        input_sizes.push(data.len());
-        compressed_sizes.push(compressed_len);
-        println!("  [xz2] Run {}: compressed to {} bytes in {:.3}ms", 
-                 run + 1, compressed_len, compress_time.as_secs_f64() * 1000.0);
-
-        // Decompression
-        decompressed.clear();
-        let start = Instant::now();
-        let mut decoder = XzDecoder::new(black_box(&compressed[..]));
-        decoder.read_to_end(&mut decompressed).unwrap();
-        let decompress_time = start.elapsed();
-        let decompressed_len = black_box(decompressed.len());
-        decompress_times.push(decompress_time);
-        println!("  [xz2] Run {}: decompressed in {:.3}ms", 
-                 run + 1, decompress_time.as_secs_f64() * 1000.0);
-        black_box(decompressed_len);
+    progress!(
+        "Original size: {} bytes ({:.2} MiB)",
+        original_size,
+        original_size as f64 / (1024.0 * 1024.0)
+    );
+    match &options.budget {
+        Some(budget) => progress!(
+            "Runs per algorithm: {:.1}s to {:.1}s (at least {}) until stderr <= {:.1}%",
+            budget.min_time.as_secs_f64(),
+            budget.max_time.as_secs_f64(),
+            num_runs,
+            budget.target_stderr * 100.0
+        ),
+        None => progress!("Number of runs per algorithm: {}", num_runs),
+    }
+    progress!("Chunk size: {} bytes", chunk_size);
+    if options.threads != [1] {
+        progress!("Threads: {:?}", options.threads);
+    }
+    if let Some(cpu) = options.pin_cpu {
+        if let Err(e) = stability::pin_cpu(cpu) {
+            eprintln!("Error: failed to pin to CPU {}: {}", cpu, e);
+            std::process::exit(1);
+        }
+        progress!("Pinned to CPU {}", cpu);
+        if options.threads.iter().any(|&threads| threads > 1) {
+            progress!("Warning: every worker thread shares CPU {}", cpu);
+        }
     }
-
-    BenchmarkResults {
-        input_sizes,
-        compressed_sizes,
-        compress_times,
-        decompress_times,
+    if options.spin_ms > 0 {
+        stability::spin(Duration::from_millis(options.spin_ms));
     }
-}
+    let memcpy_before = harness::benchmark_memcpy();
+    progress!("memcpy bandwidth: {:.0} MiB/s", memcpy_before);
+    progress!();
 
-fn benchmark_lzma_rs(data: &[u8], _original_size: usize, num_runs: usize) -> BenchmarkResults {
-    use lzma_rs::lzma_compress;
-    use lzma_rs::lzma_decompress;
+    let mut batch_number = 0;
 
-    println!("  [lzma-rs] Starting benchmark...");
-    let mut input_sizes = Vec::with_capacity(num_runs);
-    let mut compressed_sizes = Vec::with_capacity(num_runs);
-    let mut compress_times = Vec::with_capacity(num_runs);
-    let mut decompress_times = Vec::with_capacity(num_runs);
-
-    let mut compressed = vec![0u8; data.len() + 1024 * 1024];
-    let mut decompressed = vec![0u8; data.len()];
-    for byte in &mut compressed {
-        *byte = 0;
-    }
-    for byte in &mut decompressed {
-        *byte = 0;
-    }
-    compressed.clear();
-    decompressed.clear();
-
-    // Warm-up run
-    let mut warm_input = black_box(&data[..]);
-    lzma_compress(&mut warm_input, &mut compressed).unwrap();
-    let mut warm_compressed = black_box(&compressed[..]);
-    lzma_decompress(&mut warm_compressed, &mut decompressed).unwrap();
-    black_box(compressed.len());
-    black_box(decompressed.len());
-
-    for run in 0..num_runs {
-        // Compression
-        compressed.clear();
-        let start = Instant::now();
-        let mut input = black_box(&data[..]);
-        lzma_compress(&mut input, &mut compressed).unwrap();
-        let compress_time = start.elapsed();
-        let compressed_len = black_box(compressed.len());
-        compress_times.push(compress_time);
-        input_sizes.push(data.len());
-        compressed_sizes.push(compressed_len);
-        println!("  [lzma-rs] Run {}: compressed to {} bytes in {:.3}ms", 
-                 run + 1, compressed_len, compress_time.as_secs_f64() * 1000.0);
-
-        // Decompression
-        decompressed.clear();
-        let start = Instant::now();
-        let mut input = black_box(&compressed[..]);
-        lzma_decompress(&mut input, &mut decompressed).unwrap();
-        let decompress_time = start.elapsed();
-        let decompressed_len = black_box(decompressed.len());
-        decompress_times.push(decompress_time);
-        println!("  [lzma-rs] Run {}: decompressed in {:.3}ms", 
-                 run + 1, decompress_time.as_secs_f64() * 1000.0);
-        black_box(decompressed_len);
-    }
+    // --interop runs its own producer/consumer groups instead of the codecs
+    let mut interop_groups = if options.interop {
+        codecs::interop_groups()
+    } else {
+        Vec::new()
+    };
+    let mut interop_pairs = Vec::new();
+    let mut entries: Vec<Entry> = if options.interop {
+        Vec::new()
+    } else {
+        codecs
+            .iter()
+            .flat_map(|spec| {
+                options.threads.iter().map(|&threads| Entry {
+                    spec: spec.clone(),
+                    threads,
+                    codec: (threads == 1).then(|| spec.build()),
+                    results: BenchmarkResults::new(),
+                    file: BenchmarkResults::new(),
+                })
+            })
+            .collect()
+    };
 
-    BenchmarkResults {
-        input_sizes,
-        compressed_sizes,
-        compress_times,
-        decompress_times,
-    }
-}
+    let mut files = Vec::new();
+    for source in &sources {
+        if corpus {
+            progress!("File: {} ({} bytes)", source.name, source.size);
+        }
+        let mut reader = source.open().unwrap_or_else(|e| {
+            eprintln!("Error: failed to open {}", e);
+            std::process::exit(1);
+        });
+        let mut file_zero_blocks = options.skip_zero_blocks.map(ZeroBlockSkip::new);
+        // Kept bytes past the last whole chunk, waiting for the next batch
+        let mut carry = Vec::new();
+
+        // Process the file in batches of whole chunks
+        loop {
+            let mut batch = vec![0u8; batch_size];
+            let bytes_read = read_full(&mut reader, &mut batch).expect("Failed to read batch");
 
-fn benchmark_miniz_oxide(data: &[u8], _original_size: usize, num_runs: usize) -> BenchmarkResults {
-    use miniz_oxide::deflate::core::{compress, create_comp_flags_from_zip_params, CompressorOxide, TDEFLFlush, TDEFLStatus};
-    use miniz_oxide::inflate::decompress_slice_iter_to_slice;
-
-    println!("  [miniz_oxide] Starting benchmark...");
-    let mut input_sizes = Vec::with_capacity(num_runs);
-    let mut compressed_sizes = Vec::with_capacity(num_runs);
-    let mut compress_times = Vec::with_capacity(num_runs);
-    let mut decompress_times = Vec::with_capacity(num_runs);
-
-    let max_len = data.len().saturating_mul(2).saturating_add(64);
-    let mut compressed = vec![0u8; max_len];
-    let mut decompressed = vec![0u8; data.len()];
-    for byte in &mut compressed {
-        *byte = 0;
-    }
-    for byte in &mut decompressed {
-        *byte = 0;
-    }
+            if bytes_read == 0 && carry.is_empty() {
+                break;
+            }
 
-    let flags = create_comp_flags_from_zip_params(6, 0, 0);
-    let mut compressor = CompressorOxide::new(flags);
+            batch.truncate(bytes_read);
+            batch_number += 1;
+
+            if let Some(skip) = &mut file_zero_blocks {
+                let skipped_before = skip.blocks_skipped;
+                skip_zero_blocks(&mut batch, skip.block_size, skip);
+                let skipped = skip.blocks_skipped - skipped_before;
+                if skipped > 0 {
+                    progress!(
+                        "Batch {}: skipped {} all-zero blocks",
+                        batch_number,
+                        skipped
+                    );
+                }
+                // Chunk the kept bytes as if the zero blocks had been cut out
+                // of the file, so only its last chunk can be short
+                carry.append(&mut batch);
+                let whole = if bytes_read == 0 {
+                    carry.len()
+                } else {
+                    carry.len() / chunk_size * chunk_size
+                };
+                let rest = carry.split_off(whole);
+                batch = std::mem::replace(&mut carry, rest);
+                if batch.is_empty() {
+                    continue;
+                }
+            }
 
-    fn compress_into(
-        compressor: &mut CompressorOxide,
-        input: &[u8],
-        output: &mut [u8],
-    ) -> usize {
-        let mut input_remaining = input;
-        let mut out_pos = 0;
-        loop {
-            let (status, bytes_in, bytes_out) = compress(
-                compressor,
-                input_remaining,
-                &mut output[out_pos..],
-                TDEFLFlush::Finish,
+            progress!(
+                "Processing batch {} ({} bytes, {} chunks)...",
+                batch_number,
+                batch.len(),
+                batch.len().div_ceil(chunk_size)
             );
-            out_pos += bytes_out;
-            input_remaining = &input_remaining[bytes_in..];
-            match status {
-                TDEFLStatus::Done => return out_pos,
-                TDEFLStatus::Okay => continue,
-                _ => panic!("miniz_oxide compression failed"),
+
+            // Benchmark each compression algorithm on this batch, skipping any
+            // that already failed a round trip on an earlier batch or file
+            for entry in entries.iter_mut() {
+                if entry.results.failure.is_some() || entry.file.failure.is_some() {
+                    continue;
+                }
+                let results = match &mut entry.codec {
+                    Some(codec) => harness::benchmark(codec.as_mut(), &batch, &config),
+                    None => {
+                        harness::benchmark_parallel(&entry.spec, &batch, &config, entry.threads)
+                    }
+                };
+                entry.file.merge(results);
+            }
+            for group in interop_groups.iter_mut() {
+                progress!("  Interop: {}", group.format);
+                harness::interop(group, &batch, &config, &mut interop_pairs);
             }
         }
-    }
 
-    // Warm-up run
-    compressor.reset();
-    let compressed_len = compress_into(&mut compressor, black_box(data), &mut compressed);
-    let decompressed_len = decompress_slice_iter_to_slice(
-        &mut decompressed,
-        std::iter::once(black_box(&compressed[..compressed_len])),
-        false,
-        false,
-    )
-    .unwrap();
-    black_box(compressed_len);
-    black_box(decompressed_len);
-
-    for run in 0..num_runs {
-        // Compression
-        compressor.reset();
-        let start = Instant::now();
-        let compressed_len = compress_into(&mut compressor, black_box(data), &mut compressed);
-        let compress_time = start.elapsed();
-        let compressed_len = black_box(compressed_len);
-        compress_times.push(compress_time);
-        input_sizes.push(data.len());
-        compressed_sizes.push(compressed_len);
-        println!("  [miniz_oxide] Run {}: compressed to {} bytes in {:.3}ms", 
-                 run + 1, compressed_len, compress_time.as_secs_f64() * 1000.0);
-
-        // Decompression
-        let start = Instant::now();
-        let decompressed_len = decompress_slice_iter_to_slice(
-            &mut decompressed,
-            std::iter::once(black_box(&compressed[..compressed_len])),
-            false,
-            false,
-        )
-        .unwrap();
-        let decompress_time = start.elapsed();
-        decompress_times.push(decompress_time);
-        println!("  [miniz_oxide] Run {}: decompressed in {:.3}ms", 
-                 run + 1, decompress_time.as_secs_f64() * 1000.0);
-        black_box(decompressed_len);
-    }
-
-    BenchmarkResults {
-        input_sizes,
-        compressed_sizes,
-        compress_times,
-        decompress_times,
-    }
-}
-
-fn benchmark_lz4_flex(data: &[u8], original_size: usize, num_runs: usize) -> BenchmarkResults {
-    println!("  [lz4_flex] Starting benchmark...");
-    let mut input_sizes = Vec::with_capacity(num_runs);
-    let mut compressed_sizes = Vec::with_capacity(num_runs);
-    let mut compress_times = Vec::with_capacity(num_runs);
-    let mut decompress_times = Vec::with_capacity(num_runs);
-
-    let max_len = lz4_flex::block::get_maximum_output_size(data.len());
-    let mut compressed = vec![0u8; max_len];
-    let mut decompressed = vec![0u8; original_size];
-    for byte in &mut compressed {
-        *byte = 0;
-    }
-    for byte in &mut decompressed {
-        *byte = 0;
-    }
-
-    // Warm-up run
-    let compressed_len = lz4_flex::compress_into(black_box(data), &mut compressed).unwrap();
-    let decompressed_len =
-        lz4_flex::decompress_into(black_box(&compressed[..compressed_len]), &mut decompressed)
-            .unwrap();
-    black_box(compressed_len);
-    black_box(decompressed_len);
-
-    for run in 0..num_runs {
-        // Compression
-        let start = Instant::now();
-        let compressed_len = lz4_flex::compress_into(black_box(data), &mut compressed).unwrap();
-        let compress_time = start.elapsed();
-        let compressed_len = black_box(compressed_len);
-        compress_times.push(compress_time);
-        input_sizes.push(data.len());
-        compressed_sizes.push(compressed_len);
-        println!("  [lz4_flex] Run {}: compressed to {} bytes in {:.3}ms", 
-                 run + 1, compressed_len, compress_time.as_secs_f64() * 1000.0);
-
-        // Decompression
-        let start = Instant::now();
-        let decompressed_len =
-            lz4_flex::decompress_into(black_box(&compressed[..compressed_len]), &mut decompressed)
-                .unwrap();
-        let decompress_time = start.elapsed();
-        decompress_times.push(decompress_time);
-        println!("  [lz4_flex] Run {}: decompressed in {:.3}ms", 
-                 run + 1, decompress_time.as_secs_f64() * 1000.0);
-        black_box(decompressed_len);
+        if let (Some(total), Some(skip)) = (&mut zero_blocks, &file_zero_blocks) {
+            total.merge(skip);
+        }
+        let mut measurements = Vec::new();
+        for entry in entries.iter_mut() {
+            let results = std::mem::replace(&mut entry.file, BenchmarkResults::new());
+            if corpus && (results.failure.is_some() || !results.input_sizes.is_empty()) {
+                let mut file_results = results.clone();
+                file_results.sum_batches();
+                measurements.push(Measurement {
+                    algorithm: entry.spec.name().to_string(),
+                    threads: entry.threads,
+                    results: file_results,
+                });
+            }
+            entry.results.merge(results);
+        }
+        if corpus {
+            files.push(FileResults {
+                path: source.name.clone(),
+                size: source.size,
+                zero_blocks: file_zero_blocks,
+                results: measurements,
+            });
+        }
     }
 
-    BenchmarkResults {
-        input_sizes,
-        compressed_sizes,
-        compress_times,
-        decompress_times,
+    // The dictionary rows above include the chunks it was trained on, so
+    // measure each of them and its plain level on the others as well
+    if let (Some(training), false) = (&mut dictionary_training, options.interop) {
+        let plain: Vec<String> = codecs
+            .iter()
+            .filter_map(|spec| spec.name().strip_suffix(", dict)"))
+            .map(|level| format!("{})", level))
+            .collect();
+        let mut held_out: Vec<Box<dyn Codec>> = codecs
+            .iter()
+            .filter(|spec| {
+                spec.name().ends_with(", dict)") || plain.iter().any(|p| p == spec.name())
+            })
+            .map(CodecSpec::build)
+            .collect();
+        if !held_out.is_empty() {
+            progress!("Compressing the chunks left out of the dictionary sample ...");
+            training.held_out =
+                dictionary::held_out(&sources, chunk_size, options.dict_sample, &mut held_out)
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: failed to measure held-out dictionary ratios: {}", e);
+                        std::process::exit(1);
+                    });
+        }
     }
-}
 
-fn benchmark_libdeflate(data: &[u8], _original_size: usize, num_runs: usize) -> BenchmarkResults {
-    use libdeflater::{Compressor, Decompressor, CompressionLvl};
-
-    println!("  [libdeflate] Starting benchmark...");
-    let mut input_sizes = Vec::with_capacity(num_runs);
-    let mut compressed_sizes = Vec::with_capacity(num_runs);
-    let mut compress_times = Vec::with_capacity(num_runs);
-    let mut decompress_times = Vec::with_capacity(num_runs);
-
-    let mut compressor = Compressor::new(CompressionLvl::default());
-    let mut decompressor = Decompressor::new();
-    let max_sz = compressor.deflate_compress_bound(data.len());
-    let mut compressed = vec![0u8; max_sz];
-    let mut decompressed = vec![0u8; data.len()];
+    let stability = Stability {
+        pinned_cpu: options.pin_cpu,
+        spin_ms: options.spin_ms,
+        memcpy_before,
+        memcpy_after: harness::benchmark_memcpy(),
+        drift_threshold: options.drift_threshold,
+    };
+    progress!(
+        "memcpy bandwidth after the benchmark: {:.0} MiB/s ({:+.1}%)",
+        stability.memcpy_after,
+        stability.drift() * 100.0
+    );
 
-    for byte in &mut compressed {
-        *byte = 0;
-    }
-    for byte in &mut decompressed {
-        *byte = 0;
+    let mut report = Report {
+        input: input_name,
+        chunk_size,
+        num_runs,
+        budget: options.budget,
+        host: HostInfo::collect(),
+        zero_blocks,
+        io_buffer: options
+            .modes
+            .contains(&Mode::Stream)
+            .then_some(options.io_buffer),
+        dictionary: dictionary_training,
+        stability: Some(stability),
+        results: entries
+            .into_iter()
+            .map(|mut entry| {
+                entry.results.sum_batches();
+                Measurement {
+                    algorithm: entry.spec.name().to_string(),
+                    threads: entry.threads,
+                    results: entry.results,
+                }
+            })
+            .collect(),
+        files,
+        interop: interop_pairs,
+        comparison: None,
+        pareto: None,
+        costs: None,
+    };
+    if !options.interop {
+        report.pareto = Some(pareto::analyse(&report.results, &options.require));
+        report.costs = options
+            .cost_model
+            .map(|model| cost::rank(&report.results, &model, report.zero_blocks.as_ref()));
+    }
+
+    if let Some(name) = &options.save_baseline {
+        match baseline::save(name, &report) {
+            Ok(path) => progress!("Saved baseline to {}", path.display()),
+            Err(e) => {
+                eprintln!("Error: failed to save baseline: {}", e);
+                std::process::exit(1);
+            }
+        }
     }
-
-    // Warm-up run
-    let compressed_len = compressor
-        .deflate_compress(black_box(data), &mut compressed)
-        .unwrap();
-    let decompressed_len = decompressor
-        .deflate_decompress(black_box(&compressed[..compressed_len]), &mut decompressed)
-        .unwrap();
-    black_box(compressed_len);
-    black_box(decompressed_len);
-
-    for run in 0..num_runs {
-        // Compression
-        let start = Instant::now();
-        let compressed_len = compressor
-            .deflate_compress(black_box(data), &mut compressed)
-            .unwrap();
-        let compress_time = start.elapsed();
-        let compressed_len = black_box(compressed_len);
-        compress_times.push(compress_time);
-        input_sizes.push(data.len());
-        compressed_sizes.push(compressed_len);
-        println!("  [libdeflate] Run {}: compressed to {} bytes in {:.3}ms", 
-                 run + 1, compressed_len, compress_time.as_secs_f64() * 1000.0);
-
-        // Decompression
-        let start = Instant::now();
-        let decompressed_len = decompressor
-            .deflate_decompress(black_box(&compressed[..compressed_len]), &mut decompressed)
-            .unwrap();
-        let decompress_time = start.elapsed();
-        decompress_times.push(decompress_time);
-        println!("  [libdeflate] Run {}: decompressed in {:.3}ms", 
-                 run + 1, decompress_time.as_secs_f64() * 1000.0);
-        black_box(decompressed_len);
+    if let (Some(name), Some(baseline)) = (&options.compare, &baseline) {
+        report.comparison = Some(baseline::compare(
+            name,
+            baseline,
+            &report,
+            options.regression_threshold,
+        ));
+    }
+
+    let written = match &options.output {
+        Some(path) => fs::File::create(path)
+            .and_then(|mut file| report::write(options.format, &mut file, &report, options.stat)),
+        None => report::write(
+            options.format,
+            &mut io::stdout().lock(),
+            &report,
+            options.stat,
+        ),
+    };
+    if let Err(e) = written {
+        eprintln!("Error: failed to write report: {}", e);
+        std::process::exit(1);
     }
 
-    BenchmarkResults {
-        input_sizes,
-        compressed_sizes,
-        compress_times,
-        decompress_times,
+    if let Some(comparison) = &report.comparison {
+        let unconfirmed = comparison.unconfirmed().count();
+        if unconfirmed > 0 {
+            eprintln!(
+                "Warning: {} algorithm{} looked slower than baseline {}, with too few runs to tell",
+                unconfirmed,
+                if unconfirmed == 1 { "" } else { "s" },
+                comparison.baseline
+            );
+        }
+        let regressions = comparison.regressions().count();
+        if regressions > 0 {
+            eprintln!(
+                "Error: {} algorithm{} regressed against baseline {}",
+                regressions,
+                if regressions == 1 { "" } else { "s" },
+                comparison.baseline
+            );
+            std::process::exit(1);
+        }
     }
 }
 
-fn print_results(name: &str, results: &BenchmarkResults) {
-    // Guard against empty results
-    if results.input_sizes.is_empty()
-        || results.compressed_sizes.is_empty() 
-        || results.compress_times.is_empty() 
-        || results.decompress_times.is_empty() {
-        eprintln!("Warning: No results to display for {}", name);
-        return;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(batch: &[u8], block_size: usize) -> (Vec<u8>, ZeroBlockSkip) {
+        let mut batch = batch.to_vec();
+        let mut skip = ZeroBlockSkip::new(block_size);
+        skip_zero_blocks(&mut batch, block_size, &mut skip);
+        (batch, skip)
+    }
+
+    fn tally(skip: &ZeroBlockSkip) -> [usize; 4] {
+        [
+            skip.blocks_read,
+            skip.blocks_skipped,
+            skip.bytes_read,
+            skip.bytes_skipped,
+        ]
+    }
+
+    #[test]
+    fn keeps_blocks_with_any_nonzero_byte_in_order() {
+        let mut batch = vec![0u8; 16];
+        batch[1] = 1;
+        batch[8..12].copy_from_slice(&[2, 2, 2, 2]);
+        batch[15] = 3;
+        let (kept, skip) = run(&batch, 4);
+        assert_eq!(kept, [0, 1, 0, 0, 2, 2, 2, 2, 0, 0, 0, 3]);
+        assert_eq!(tally(&skip), [4, 1, 16, 4]);
+    }
+
+    #[test]
+    fn trailing_partial_zero_block_is_skipped() {
+        let mut batch = vec![7u8; 8];
+        batch.extend_from_slice(&[0, 0, 0]);
+        let (kept, skip) = run(&batch, 4);
+        assert_eq!(kept, [7; 8]);
+        assert_eq!(tally(&skip), [3, 1, 11, 3]);
+    }
+
+    #[test]
+    fn trailing_partial_block_with_data_is_kept() {
+        let (kept, skip) = run(&[0, 0, 0, 0, 0, 5], 4);
+        assert_eq!(kept, [0, 5]);
+        assert_eq!(tally(&skip), [2, 1, 6, 4]);
+    }
+
+    #[test]
+    fn all_zero_and_empty_batches() {
+        let (kept, skip) = run(&[0; 10], 4);
+        assert!(kept.is_empty());
+        assert_eq!(tally(&skip), [3, 3, 10, 10]);
+        assert_eq!(skip.scale(), f64::INFINITY);
+
+        let (kept, skip) = run(&[], 4);
+        assert!(kept.is_empty());
+        assert_eq!(tally(&skip), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn tallies_accumulate_across_batches() {
+        let mut skip = ZeroBlockSkip::new(4);
+        for batch in [vec![0u8; 8], vec![1u8; 6]] {
+            let mut batch = batch;
+            skip_zero_blocks(&mut batch, 4, &mut skip);
+        }
+        assert_eq!(tally(&skip), [4, 2, 14, 8]);
+        assert_eq!(skip.scale(), 14.0 / 6.0);
     }
-
-    let total_input_size = results.input_sizes.iter().sum::<usize>() as f64;
-    let total_compressed_size = results.compressed_sizes.iter().sum::<usize>() as f64;
-    let ratio = if total_compressed_size > 0.0 {
-        total_input_size / total_compressed_size
-    } else {
-        0.0
-    };
-
-    let total_compress_time = results
-        .compress_times
-        .iter()
-        .map(|d| d.as_secs_f64())
-        .sum::<f64>();
-    let compress_throughput = if total_compress_time > 0.0 {
-        (total_input_size / (1024.0 * 1024.0)) / total_compress_time
-    } else {
-        f64::INFINITY
-    };
-
-    let total_decompress_time = results
-        .decompress_times
-        .iter()
-        .map(|d| d.as_secs_f64())
-        .sum::<f64>();
-    let decompress_throughput = if total_decompress_time > 0.0 {
-        (total_input_size / (1024.0 * 1024.0)) / total_decompress_time
-    } else {
-        f64::INFINITY
-    };
-
-    println!(
-        "| {:<20} | {:>6.2} | {:>16.2} | {:>18.2} |",
-        name, ratio, compress_throughput, decompress_throughput
-    );
 }
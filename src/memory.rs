@@ -0,0 +1,213 @@
+//! Memory accounting: a global allocator that counts heap allocations on top
+//! of the system allocator, and peak RSS from `/proc/self/status`.
+//!
+//! The allocator only sees allocations made from Rust. The C libraries (zstd,
+//! liblzma, liblz4, libdeflate) call `malloc` directly, so their working
+//! memory shows up in the RSS figures instead.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// Whether a [`Tracker`] is running. The counters are left alone otherwise,
+/// so that timed passes pay for one relaxed load per call and nothing more.
+static TRACKING: AtomicBool = AtomicBool::new(false);
+/// Bytes allocated minus bytes freed while tracking. Memory allocated before
+/// and freed during a pass takes it below zero.
+static CURRENT: AtomicIsize = AtomicIsize::new(0);
+static PEAK: AtomicIsize = AtomicIsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator plus relaxed atomic counters, updated while a
+/// [`Tracker`] runs: net bytes allocated, the most of them at any point, and
+/// running totals of bytes and calls. Installed as the global allocator in
+/// `main.rs`.
+pub struct CountingAllocator;
+
+fn tracking() -> bool {
+    TRACKING.load(Ordering::Relaxed)
+}
+
+fn record_alloc(size: usize) {
+    if !tracking() {
+        return;
+    }
+    ALLOCATED.fetch_add(size, Ordering::Relaxed);
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let current = CURRENT.fetch_add(size as isize, Ordering::Relaxed) + size as isize;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+}
+
+fn record_free(size: usize) {
+    if tracking() {
+        CURRENT.fetch_sub(size as isize, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record_free(layout.size());
+    }
+
+    // A reallocation counts as one allocation of the new size, since it may
+    // have to copy into fresh memory
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_free(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Heap and RSS usage of one direction (compression or decompression) of a
+/// codec, measured over its untimed warm-up pass on each batch. Memory a
+/// codec keeps between calls is only counted in the pass that allocated it:
+/// state built along with the codec does not show, state built on first use
+/// shows in the first warm-up.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    /// Passes measured, warm-up included.
+    pub passes: usize,
+    /// Most Rust heap any pass held on top of what was live when it started,
+    /// i.e. the codec's working memory beyond the harness's own buffers.
+    pub peak_heap: usize,
+    /// Bytes requested from the allocator, summed over all passes.
+    pub allocated_bytes: usize,
+    /// Allocator calls, summed over all passes.
+    pub allocations: usize,
+    /// Highest resident set size of the whole process during any pass,
+    /// input and output buffers included. `None` where the peak could not be
+    /// reset before each pass.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_rss: Option<usize>,
+    /// Most the process's RSS grew during any pass above its level when the
+    /// pass started, which includes memory C libraries get from `malloc`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss_growth: Option<usize>,
+}
+
+impl MemoryUsage {
+    pub fn merge(&mut self, other: MemoryUsage) {
+        self.passes += other.passes;
+        self.peak_heap = self.peak_heap.max(other.peak_heap);
+        self.allocated_bytes += other.allocated_bytes;
+        self.allocations += other.allocations;
+        self.peak_rss = max_option(self.peak_rss, other.peak_rss);
+        self.rss_growth = max_option(self.rss_growth, other.rss_growth);
+    }
+
+    /// Bytes allocated per pass.
+    pub fn allocated_per_pass(&self) -> f64 {
+        self.allocated_bytes as f64 / self.passes.max(1) as f64
+    }
+
+    /// Allocator calls per pass.
+    pub fn allocations_per_pass(&self) -> f64 {
+        self.allocations as f64 / self.passes.max(1) as f64
+    }
+}
+
+pub fn max_option(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Hands free heap memory back to the OS, so that a pass touching memory an
+/// earlier codec freed still grows the RSS. glibc only.
+fn trim_heap() {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    // SAFETY: malloc_trim only releases memory that is free
+    unsafe {
+        libc::malloc_trim(0);
+    }
+}
+
+/// A `kB` field of `/proc/self/status`, in bytes.
+fn status_field(name: &str) -> Option<usize> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with(name))?;
+    let kib: usize = line[name.len()..]
+        .trim_start_matches(':')
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// Counters at the start of one pass. Only wrap untimed passes around it:
+/// starting it trims the heap, returning freed pages to the kernel for the
+/// pass to fault in again, and the allocator counts every call meanwhile.
+pub struct Tracker {
+    allocated: usize,
+    allocations: usize,
+    /// RSS at the start, if the peak RSS could be reset to it.
+    rss: Option<usize>,
+}
+
+impl Tracker {
+    /// Starts counting allocations from zero and, where the kernel allows it,
+    /// resets the process's peak RSS (`VmHWM`) to its current RSS.
+    pub fn start() -> Self {
+        // First, so that its own allocations are not counted
+        trim_heap();
+        let rss = fs::write("/proc/self/clear_refs", "5")
+            .ok()
+            .and_then(|()| status_field("VmRSS"));
+        CURRENT.store(0, Ordering::Relaxed);
+        PEAK.store(0, Ordering::Relaxed);
+        let tracker = Self {
+            allocated: ALLOCATED.load(Ordering::Relaxed),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            rss,
+        };
+        TRACKING.store(true, Ordering::Relaxed);
+        tracker
+    }
+
+    /// What the pass allocated since [`Tracker::start`], added to `usage`.
+    pub fn finish(self, usage: &mut MemoryUsage) {
+        // First, so that reading `/proc` is not counted
+        TRACKING.store(false, Ordering::Relaxed);
+        let peak_heap = PEAK.load(Ordering::Relaxed).max(0) as usize;
+        let allocated_bytes = ALLOCATED.load(Ordering::Relaxed) - self.allocated;
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - self.allocations;
+        let peak_rss = self.rss.and_then(|_| status_field("VmHWM"));
+        usage.merge(MemoryUsage {
+            passes: 1,
+            peak_heap,
+            allocated_bytes,
+            allocations,
+            peak_rss,
+            rss_growth: self
+                .rss
+                .zip(peak_rss)
+                .map(|(start, peak)| peak.saturating_sub(start)),
+        });
+    }
+}
@@ -0,0 +1,282 @@
+//! Pareto frontiers of ratio against compression and decompression speed,
+//! and a recommendation under `--require` constraints.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::report::{self, Measurement};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    Ratio,
+    Compress,
+    Decompress,
+}
+
+impl Metric {
+    fn name(self) -> &'static str {
+        match self {
+            Metric::Ratio => "ratio",
+            Metric::Compress => "compress",
+            Metric::Decompress => "decompress",
+        }
+    }
+}
+
+/// One `--require` constraint, such as `compress>=500MiB/s` or `ratio>=3`.
+/// Speeds are kept in MiB/s.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Requirement {
+    pub metric: Metric,
+    /// `true` for `>=`, `false` for `<=`.
+    pub at_least: bool,
+    pub value: f64,
+}
+
+impl Requirement {
+    /// Parses `METRIC>=VALUE` or `METRIC<=VALUE`. Speeds take an optional
+    /// `KiB/s`, `MiB/s` (default) or `GiB/s` unit; ratios are plain numbers.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "--require entry '{}' must look like compress>=500MiB/s, decompress>=1GiB/s \
+                 or ratio>=3",
+                s
+            )
+        };
+        let (metric, value, at_least) = match (s.split_once(">="), s.split_once("<=")) {
+            (Some((metric, value)), _) => (metric, value, true),
+            (None, Some((metric, value))) => (metric, value, false),
+            (None, None) => return Err(invalid()),
+        };
+        let metric = match metric.trim() {
+            "ratio" => Metric::Ratio,
+            "compress" => Metric::Compress,
+            "decompress" => Metric::Decompress,
+            _ => return Err(invalid()),
+        };
+        let value = value.trim();
+        let (number, scale) = if metric == Metric::Ratio {
+            (value, 1.0)
+        } else {
+            let lower = value.to_ascii_lowercase();
+            [("kib/s", 1.0 / 1024.0), ("mib/s", 1.0), ("gib/s", 1024.0)]
+                .iter()
+                .find_map(|&(unit, scale)| {
+                    lower
+                        .strip_suffix(unit)
+                        .map(|_| (&value[..value.len() - unit.len()], scale))
+                })
+                .unwrap_or((value, 1.0))
+        };
+        let value: f64 = number
+            .trim()
+            .parse()
+            .ok()
+            .filter(|v: &f64| *v >= 0.0 && v.is_finite())
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            metric,
+            at_least,
+            value: value * scale,
+        })
+    }
+
+    fn met_by(&self, codec: &ParetoPoint) -> bool {
+        let actual = match self.metric {
+            Metric::Ratio => codec.ratio,
+            Metric::Compress => codec.compress,
+            Metric::Decompress => codec.decompress,
+        };
+        if self.at_least {
+            actual >= self.value
+        } else {
+            actual <= self.value
+        }
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = if self.at_least { ">=" } else { "<=" };
+        match self.metric {
+            Metric::Ratio => write!(f, "ratio{}{}", op, self.value),
+            metric => write!(f, "{}{}{}MiB/s", metric.name(), op, self.value),
+        }
+    }
+}
+
+/// A codec that completed every round trip, with what dominates it on each
+/// frontier.
+#[derive(Serialize, Deserialize)]
+pub struct ParetoPoint {
+    pub label: String,
+    pub ratio: f64,
+    /// Aggregate throughput in MiB/s.
+    pub compress: f64,
+    pub decompress: f64,
+    /// A codec with at least this ratio and compression speed and more of
+    /// one of them, if any: this one is then off the frontier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress_dominated_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decompress_dominated_by: Option<String>,
+}
+
+/// Both frontiers and the best codec under the requirements.
+#[derive(Serialize, Deserialize)]
+pub struct Pareto {
+    pub codecs: Vec<ParetoPoint>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requirements: Vec<Requirement>,
+    /// The highest-ratio codec meeting every requirement, ties going to the
+    /// faster compressor; `None` if there are no requirements or none meets
+    /// them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommendation: Option<String>,
+}
+
+/// The codec dominating `codecs[index]` on the ratio/`speed` plane with the
+/// highest ratio, if any.
+fn dominated_by(
+    codecs: &[ParetoPoint],
+    index: usize,
+    speed: fn(&ParetoPoint) -> f64,
+) -> Option<String> {
+    let point = &codecs[index];
+    codecs
+        .iter()
+        .filter(|other| {
+            other.ratio >= point.ratio
+                && speed(other) >= speed(point)
+                && (other.ratio > point.ratio || speed(other) > speed(point))
+        })
+        .max_by(|a, b| a.ratio.total_cmp(&b.ratio))
+        .map(|other| other.label.clone())
+}
+
+/// Places every codec of `results` that did not fail on both frontiers and
+/// picks the one to recommend under `requirements`. `memcpy` is left out: it
+/// would dominate every fast codec and be recommended for speed alone.
+pub fn analyse(results: &[Measurement], requirements: &[Requirement]) -> Pareto {
+    let mut codecs: Vec<ParetoPoint> = results
+        .iter()
+        .filter(|m| !m.is_reference())
+        .filter(|m| m.results.failure.is_none() && !m.results.input_sizes.is_empty())
+        .map(|measurement| {
+            let (compress, decompress) = report::aggregate_throughput(&measurement.results);
+            ParetoPoint {
+                label: measurement.label(),
+                ratio: report::ratio(&measurement.results),
+                compress,
+                decompress,
+                compress_dominated_by: None,
+                decompress_dominated_by: None,
+            }
+        })
+        .collect();
+    for index in 0..codecs.len() {
+        codecs[index].compress_dominated_by = dominated_by(&codecs, index, |c| c.compress);
+        codecs[index].decompress_dominated_by = dominated_by(&codecs, index, |c| c.decompress);
+    }
+
+    let recommendation = if requirements.is_empty() {
+        None
+    } else {
+        codecs
+            .iter()
+            .filter(|codec| requirements.iter().all(|r| r.met_by(codec)))
+            .max_by(|a, b| {
+                a.ratio
+                    .total_cmp(&b.ratio)
+                    .then(a.compress.total_cmp(&b.compress))
+            })
+            .map(|codec| codec.label.clone())
+    };
+    Pareto {
+        codecs,
+        requirements: requirements.to_vec(),
+        recommendation,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> (Metric, bool, f64) {
+        let requirement = Requirement::parse(s).unwrap();
+        (requirement.metric, requirement.at_least, requirement.value)
+    }
+
+    #[test]
+    fn ratios() {
+        assert!(parse("ratio>=3") == (Metric::Ratio, true, 3.0));
+        assert!(parse("ratio<=2.5") == (Metric::Ratio, false, 2.5));
+        assert!(parse(" ratio >= 3 ") == (Metric::Ratio, true, 3.0));
+    }
+
+    #[test]
+    fn speeds_in_mib_per_second() {
+        assert!(parse("compress>=500") == (Metric::Compress, true, 500.0));
+        assert!(parse("compress>=500MiB/s") == (Metric::Compress, true, 500.0));
+        assert!(parse("decompress>=1GiB/s") == (Metric::Decompress, true, 1024.0));
+        assert!(parse("decompress<=512KiB/s") == (Metric::Decompress, false, 0.5));
+        // Units are case-insensitive, and may be spaced from the number
+        assert!(parse("compress>=2 gib/s") == (Metric::Compress, true, 2048.0));
+        assert!(parse("compress>=0") == (Metric::Compress, true, 0.0));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "ratio>=3",
+            "ratio<=2.5",
+            "compress>=500MiB/s",
+            "decompress<=0.5MiB/s",
+        ] {
+            assert_eq!(Requirement::parse(s).unwrap().to_string(), s);
+        }
+        assert_eq!(
+            Requirement::parse("decompress>=1GiB/s")
+                .unwrap()
+                .to_string(),
+            "decompress>=1024MiB/s"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        for s in [
+            "",
+            "ratio",
+            "ratio=3",
+            "ratio>3",
+            "ratio=>3",
+            "ratio>=",
+            ">=3",
+            "speed>=500MiB/s",
+            "Ratio>=3",
+            "ratio>=3MiB/s",
+            "compress>=500MB/s",
+            "compress>=MiB/s",
+            "compress>=<=5",
+        ] {
+            assert!(Requirement::parse(s).is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn rejects_negative_and_non_finite_values() {
+        for s in [
+            "ratio>=-1",
+            "compress>=-5MiB/s",
+            "ratio>=nan",
+            "decompress<=inf",
+        ] {
+            assert!(Requirement::parse(s).is_err(), "{:?}", s);
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Hardware performance counters around timed sections, through Linux's
+//! `perf_event_open`. Collection needs the `perf` cargo feature; without it,
+//! or where the kernel or hypervisor does not expose the counters, nothing is
+//! collected and the report leaves the counter table out.
+
+use serde::{Deserialize, Serialize};
+
+/// User-space hardware counter totals for one direction of a codec, over
+/// its timed runs.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PerfCounts {
+    pub cycles: u64,
+    pub instructions: u64,
+    /// Last-level cache misses, where the CPU exposes them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llc_misses: Option<u64>,
+}
+
+impl PerfCounts {
+    pub fn merge(&mut self, other: PerfCounts) {
+        self.cycles += other.cycles;
+        self.instructions += other.instructions;
+        self.llc_misses = match (self.llc_misses, other.llc_misses) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+    }
+
+    /// Instructions per cycle.
+    pub fn ipc(&self) -> f64 {
+        self.instructions as f64 / self.cycles as f64
+    }
+}
+
+/// Adds `counts` to `total`, which stays `None` until counters were read.
+pub fn merge(total: &mut Option<PerfCounts>, counts: PerfCounts) {
+    total.get_or_insert_with(PerfCounts::default).merge(counts);
+}
+
+#[cfg(feature = "perf")]
+pub use self::sys::Counters;
+
+/// Stand-in when the `perf` feature is off: never opens.
+#[cfg(not(feature = "perf"))]
+pub struct Counters;
+
+#[cfg(not(feature = "perf"))]
+impl Counters {
+    pub fn open() -> Option<Self> {
+        None
+    }
+
+    pub fn start(&self) {}
+
+    pub fn stop(&self, _total: &mut Option<PerfCounts>) {}
+}
+
+#[cfg(feature = "perf")]
+mod sys {
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    use super::{merge, PerfCounts};
+
+    const PERF_TYPE_HARDWARE: u32 = 0;
+    const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+    const PERF_COUNT_HW_INSTRUCTIONS: u64 = 1;
+    /// Usually last-level cache misses.
+    const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+
+    const PERF_FORMAT_TOTAL_TIME_ENABLED: u64 = 1 << 0;
+    const PERF_FORMAT_TOTAL_TIME_RUNNING: u64 = 1 << 1;
+
+    const FLAG_DISABLED: u64 = 1 << 0;
+    const FLAG_INHERIT: u64 = 1 << 1;
+    const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+    const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+    const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+    const PERF_EVENT_IOC_ENABLE: libc::c_ulong = 0x2400;
+    const PERF_EVENT_IOC_DISABLE: libc::c_ulong = 0x2401;
+    const PERF_EVENT_IOC_RESET: libc::c_ulong = 0x2403;
+
+    /// The first version of `struct perf_event_attr` (`PERF_ATTR_SIZE_VER0`),
+    /// which every kernel accepts; the flag bitfield is a plain `u64`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct PerfEventAttr {
+        kind: u32,
+        size: u32,
+        config: u64,
+        sample_period: u64,
+        sample_type: u64,
+        read_format: u64,
+        flags: u64,
+        wakeup_events: u32,
+        bp_type: u32,
+        config1: u64,
+    }
+
+    /// Opens a disabled user-space counter for this process, inherited by
+    /// threads it creates from now on.
+    fn open_counter(config: u64) -> Option<File> {
+        let attr = PerfEventAttr {
+            kind: PERF_TYPE_HARDWARE,
+            size: std::mem::size_of::<PerfEventAttr>() as u32,
+            config,
+            read_format: PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING,
+            flags: FLAG_DISABLED | FLAG_INHERIT | FLAG_EXCLUDE_KERNEL | FLAG_EXCLUDE_HV,
+            ..Default::default()
+        };
+        // SAFETY: `attr` outlives the call and its size field matches it
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_perf_event_open,
+                &attr as *const PerfEventAttr,
+                0,  // this process
+                -1, // on any CPU
+                -1, // no group
+                PERF_FLAG_FD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return None;
+        }
+        // SAFETY: the kernel just handed us this descriptor
+        Some(unsafe { File::from_raw_fd(fd as i32) })
+    }
+
+    fn ioctl(file: &File, request: libc::c_ulong) {
+        // SAFETY: the perf ioctls used here take no argument
+        unsafe {
+            libc::ioctl(file.as_raw_fd(), request, 0);
+        }
+    }
+
+    /// The counter's value, scaled up when the kernel had to multiplex it
+    /// with other events.
+    fn read_counter(mut file: &File) -> Option<u64> {
+        let mut buf = [0u8; 24];
+        file.read_exact(&mut buf).ok()?;
+        let word = |i: usize| u64::from_ne_bytes(buf[i * 8..i * 8 + 8].try_into().unwrap());
+        let (value, enabled, running) = (word(0), word(1), word(2));
+        if running == 0 {
+            return None;
+        }
+        Some((value as f64 * enabled as f64 / running as f64) as u64)
+    }
+
+    /// Cycle, instruction and LLC miss counters for the whole process.
+    pub struct Counters {
+        cycles: File,
+        instructions: File,
+        llc_misses: Option<File>,
+    }
+
+    impl Counters {
+        /// Opens the counters, or `None` when cycles or instructions are not
+        /// available, e.g. inside most VMs or with a restrictive
+        /// `perf_event_paranoid`. Open them before starting worker threads,
+        /// so that the workers inherit them.
+        pub fn open() -> Option<Self> {
+            Some(Self {
+                cycles: open_counter(PERF_COUNT_HW_CPU_CYCLES)?,
+                instructions: open_counter(PERF_COUNT_HW_INSTRUCTIONS)?,
+                llc_misses: open_counter(PERF_COUNT_HW_CACHE_MISSES),
+            })
+        }
+
+        fn files(&self) -> impl Iterator<Item = &File> {
+            [Some(&self.cycles), Some(&self.instructions)]
+                .into_iter()
+                .chain([self.llc_misses.as_ref()])
+                .flatten()
+        }
+
+        /// Zeroes and starts every counter.
+        pub fn start(&self) {
+            for file in self.files() {
+                ioctl(file, PERF_EVENT_IOC_RESET);
+                ioctl(file, PERF_EVENT_IOC_ENABLE);
+            }
+        }
+
+        /// Stops every counter and adds what it counted to `total`.
+        pub fn stop(&self, total: &mut Option<PerfCounts>) {
+            for file in self.files() {
+                ioctl(file, PERF_EVENT_IOC_DISABLE);
+            }
+            let (Some(cycles), Some(instructions)) =
+                (read_counter(&self.cycles), read_counter(&self.instructions))
+            else {
+                return;
+            };
+            merge(
+                total,
+                PerfCounts {
+                    cycles,
+                    instructions,
+                    llc_misses: self.llc_misses.as_ref().and_then(read_counter),
+                },
+            );
+        }
+    }
+}
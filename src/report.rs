@@ -0,0 +1,1617 @@
+//! Rendering aggregated results as a Markdown table, JSON or CSV.
+
+use std::io::{self, Write};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::baseline::Comparison;
+use crate::cost::CostReport;
+use crate::cpu::{CpuUsage, DIVERGENCE_THRESHOLD};
+use crate::harness::{BenchmarkResults, DecodeMeasurement, InteropPair, RunBudget};
+use crate::host::HostInfo;
+use crate::memory::MemoryUsage;
+use crate::pareto::Pareto;
+use crate::perf::PerfCounts;
+use crate::stability::Stability;
+use crate::stats::{self, Stat};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Json,
+    Csv,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "markdown" | "md" => Some(Format::Markdown),
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// One codec's label and every run measured for it.
+#[derive(Serialize, Deserialize)]
+pub struct Measurement {
+    pub algorithm: String,
+    /// Worker threads the chunks were spread across.
+    #[serde(default = "one")]
+    pub threads: usize,
+    #[serde(flatten)]
+    pub results: BenchmarkResults,
+}
+
+impl Measurement {
+    /// The algorithm, qualified with the thread count when it ran on more
+    /// than one thread, e.g. `lz4 x4`.
+    pub fn label(&self) -> String {
+        Self::label_for(&self.algorithm, self.threads)
+    }
+
+    /// Whether this is a `memcpy` row, the bandwidth reference rather than a
+    /// compressor anyone would pick.
+    pub fn is_reference(&self) -> bool {
+        self.algorithm == "memcpy" || self.algorithm.starts_with("memcpy (")
+    }
+
+    pub fn label_for(algorithm: &str, threads: usize) -> String {
+        if threads == 1 {
+            algorithm.to_string()
+        } else {
+            format!("{} x{}", algorithm, threads)
+        }
+    }
+}
+
+fn one() -> usize {
+    1
+}
+
+/// All-zero blocks dropped from the input before benchmarking, the way ubiblk
+/// never compresses or uploads them.
+#[derive(Serialize, Deserialize)]
+pub struct ZeroBlockSkip {
+    pub block_size: usize,
+    pub blocks_read: usize,
+    pub blocks_skipped: usize,
+    pub bytes_read: usize,
+    pub bytes_skipped: usize,
+}
+
+impl ZeroBlockSkip {
+    pub fn new(block_size: usize) -> Self {
+        Self {
+            block_size,
+            blocks_read: 0,
+            blocks_skipped: 0,
+            bytes_read: 0,
+            bytes_skipped: 0,
+        }
+    }
+
+    pub fn merge(&mut self, other: &ZeroBlockSkip) {
+        self.blocks_read += other.blocks_read;
+        self.blocks_skipped += other.blocks_skipped;
+        self.bytes_read += other.bytes_read;
+        self.bytes_skipped += other.bytes_skipped;
+    }
+
+    /// Factor turning a ratio over the benchmarked bytes into one over every
+    /// byte read, i.e. counting skipped blocks as compressing to nothing.
+    pub fn scale(&self) -> f64 {
+        let kept = self.bytes_read - self.bytes_skipped;
+        if kept > 0 {
+            self.bytes_read as f64 / kept as f64
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+/// How the zstd dictionary used by the `zstd (level N, dict)` rows was
+/// trained.
+#[derive(Serialize, Deserialize)]
+pub struct DictionaryTraining {
+    /// Dictionary size requested with `--zstd-dict`.
+    pub max_size: usize,
+    /// Size of the dictionary actually trained.
+    pub size: usize,
+    /// Chunks in the input, of which `samples` were trained on.
+    pub chunks: usize,
+    pub samples: usize,
+    pub sample_bytes: usize,
+    pub training_ns: u64,
+    /// Dictionary rows and their plain levels on the chunks left out of the
+    /// sample.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub held_out: Vec<HeldOut>,
+}
+
+/// One codec's compressed size over the chunks a dictionary was not trained
+/// on.
+#[derive(Serialize, Deserialize)]
+pub struct HeldOut {
+    pub algorithm: String,
+    pub input_bytes: usize,
+    pub compressed_bytes: usize,
+}
+
+impl HeldOut {
+    fn ratio(&self) -> Option<f64> {
+        (self.compressed_bytes > 0).then(|| self.input_bytes as f64 / self.compressed_bytes as f64)
+    }
+}
+
+/// One file of a corpus and what every codec measured on it.
+#[derive(Serialize, Deserialize)]
+pub struct FileResults {
+    pub path: String,
+    pub size: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zero_blocks: Option<ZeroBlockSkip>,
+    /// Codecs that ran on this file; one that failed on an earlier file is
+    /// left out.
+    pub results: Vec<Measurement>,
+}
+
+/// Everything a single invocation measured, plus enough context to tell
+/// where and how.
+#[derive(Serialize, Deserialize)]
+pub struct Report {
+    pub input: String,
+    pub chunk_size: usize,
+    pub num_runs: usize,
+    /// Time limits that made run counts adaptive, with `num_runs` the minimum.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<RunBudget>,
+    pub host: HostInfo,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zero_blocks: Option<ZeroBlockSkip>,
+    /// Size of each read or write made by stream variants, if any ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io_buffer: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<DictionaryTraining>,
+    /// CPU pinning and the memcpy bandwidth check around the benchmark.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stability: Option<Stability>,
+    pub results: Vec<Measurement>,
+    /// Each file's share of `results` when the input is several files.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<FileResults>,
+    /// Producer/consumer pairs measured by `--interop`, which replaces the
+    /// per-codec results.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interop: Vec<InteropPair>,
+    /// This run against the baseline given to `--compare`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<Comparison>,
+    /// Frontiers of ratio against speed and the `--require` recommendation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pareto: Option<Pareto>,
+    /// Algorithms ranked by the `--cost-model` workload.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub costs: Option<CostReport>,
+}
+
+/// A pre-compressed file given to the `decompress` subcommand.
+#[derive(Serialize, Deserialize)]
+pub struct DecodeInput {
+    pub path: String,
+    /// Detected format, if the magic bytes matched one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    pub compressed_size: usize,
+    pub decompressed_size: usize,
+    /// Why the file was left out of the measurements.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+}
+
+/// Everything a `decompress` invocation measured.
+#[derive(Serialize, Deserialize)]
+pub struct DecodeReport {
+    pub inputs: Vec<DecodeInput>,
+    pub num_runs: usize,
+    pub host: HostInfo,
+    pub results: Vec<DecodeMeasurement>,
+}
+
+pub fn write(format: Format, out: &mut dyn Write, report: &Report, stat: Stat) -> io::Result<()> {
+    match format {
+        Format::Markdown => write_markdown(out, report, stat),
+        Format::Json => write_json(out, report),
+        Format::Csv => write_csv(out, report),
+    }
+}
+
+pub fn write_decode(
+    format: Format,
+    out: &mut dyn Write,
+    report: &DecodeReport,
+    stat: Stat,
+) -> io::Result<()> {
+    match format {
+        Format::Markdown => write_decode_markdown(out, report, stat),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, report)?;
+            writeln!(out)
+        }
+        Format::Csv => write_decode_csv(out, report),
+    }
+}
+
+/// A Markdown table with a left-aligned first column and right-aligned
+/// numeric columns, each as wide as its widest cell.
+struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn new<S: Into<String>>(headers: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            headers: headers.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        let widths: Vec<usize> = (0..self.headers.len())
+            .map(|col| {
+                // Keep the classic 20-character name and 6-character ratio columns
+                let min = if col == 0 { 20 } else { 6 };
+                self.rows
+                    .iter()
+                    .map(|row| row[col].len())
+                    .chain([self.headers[col].len(), min])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let line = |out: &mut dyn Write, cells: &[String], fill: bool| -> io::Result<()> {
+            for (col, (cell, &width)) in cells.iter().zip(&widths).enumerate() {
+                match (fill, col) {
+                    (true, _) => write!(out, "| {:-<width$} ", "")?,
+                    (false, 0) => write!(out, "| {:<width$} ", cell)?,
+                    (false, _) => write!(out, "| {:>width$} ", cell)?,
+                }
+            }
+            writeln!(out, "|")
+        };
+
+        line(out, &self.headers, false)?;
+        line(out, &self.headers, true)?;
+        for row in &self.rows {
+            line(out, row, false)?;
+        }
+        Ok(())
+    }
+}
+
+/// The pinning and memcpy drift lines above the results table, with a
+/// warning when the host's memory bandwidth changed during the run.
+fn write_stability(out: &mut dyn Write, stability: &Stability) -> io::Result<()> {
+    if let Some(cpu) = stability.pinned_cpu {
+        writeln!(out, "Pinned to CPU {}.", cpu)?;
+    }
+    writeln!(
+        out,
+        "memcpy bandwidth: {:.0} MiB/s before, {:.0} MiB/s after ({:+.1}%).",
+        stability.memcpy_before,
+        stability.memcpy_after,
+        stability.drift() * 100.0
+    )?;
+    if stability.drifted() {
+        writeln!(
+            out,
+            "Warning: memcpy bandwidth drifted by more than {:.1}%, a sign of thermal \
+             throttling or noisy neighbours; results may not be comparable.",
+            stability.drift_threshold * 100.0
+        )?;
+    }
+    writeln!(out)
+}
+
+/// The main results table over `results`, either the whole input's or one
+/// file's, whose zero-block skipping `zero_blocks` describes.
+fn results_table(
+    report: &Report,
+    results: &[Measurement],
+    zero_blocks: Option<&ZeroBlockSkip>,
+    stat: Stat,
+) -> Table {
+    let (compress_header, decompress_header) = match stat {
+        Stat::Total => (
+            "Compress (MiB/s)".to_string(),
+            "Decompress (MiB/s)".to_string(),
+        ),
+        stat => (
+            format!("Compress {} (MiB/s)", stat.label()),
+            format!("Decompress {} (MiB/s)", stat.label()),
+        ),
+    };
+    let mut headers = vec![
+        "Algorithm".to_string(),
+        "Ratio".to_string(),
+        compress_header,
+        decompress_header,
+    ];
+    let threaded = report.results.iter().any(|m| m.threads != 1);
+    if zero_blocks.is_some() {
+        headers.insert(2, "Eff. Ratio".to_string());
+    }
+    if threaded {
+        headers.insert(1, "Threads".to_string());
+    }
+    if report.budget.is_some() {
+        headers.push("Runs".to_string());
+    }
+    let mut table = Table::new(headers);
+
+    for measurement in results {
+        if let Some(mut row) = markdown_row(zero_blocks, measurement, stat) {
+            if threaded {
+                row.insert(1, measurement.threads.to_string());
+            }
+            if report.budget.is_some() {
+                let runs = &measurement.results;
+                let cut = if runs.hit_max_time { " *" } else { "" };
+                row.push(format!("{}{}", runs.input_sizes.len(), cut));
+            }
+            table.row(row);
+        }
+    }
+    table
+}
+
+/// Writes the results table, with throughput columns showing `stat`, followed
+/// by per-run statistics when any codec ran more than once.
+pub fn write_markdown(out: &mut dyn Write, report: &Report, stat: Stat) -> io::Result<()> {
+    if !report.interop.is_empty() {
+        return write_interop_tables(out, &report.interop);
+    }
+    let results = &report.results;
+    let threaded = results.iter().any(|m| m.threads != 1);
+    let table = results_table(report, results, report.zero_blocks.as_ref(), stat);
+
+    writeln!(out)?;
+    if let Some(stability) = &report.stability {
+        write_stability(out, stability)?;
+    }
+    if let Some(io_buffer) = report.io_buffer {
+        writeln!(
+            out,
+            "Stream variants write and read in pieces of {} bytes.",
+            io_buffer
+        )?;
+        writeln!(out)?;
+    }
+    if let Some(zero_blocks) = &report.zero_blocks {
+        writeln!(
+            out,
+            "Skipped {} of {} all-zero blocks of {} bytes ({:.2} MiB); Eff. Ratio counts them as free.",
+            zero_blocks.blocks_skipped,
+            zero_blocks.blocks_read,
+            zero_blocks.block_size,
+            zero_blocks.bytes_skipped as f64 / (1024.0 * 1024.0)
+        )?;
+        writeln!(out)?;
+    }
+    if let Some(budget) = &report.budget {
+        writeln!(
+            out,
+            "Each algorithm ran for {:.1}s to {:.1}s per batch (at least {} run{}), until the \
+             relative standard error of its mean throughput was at most {:.1}%.",
+            budget.min_time.as_secs_f64(),
+            budget.max_time.as_secs_f64(),
+            report.num_runs,
+            if report.num_runs == 1 { "" } else { "s" },
+            budget.target_stderr * 100.0
+        )?;
+        writeln!(out)?;
+    }
+    if !report.files.is_empty() {
+        writeln!(
+            out,
+            "Corpus of {} files, {:.2} MiB:",
+            report.files.len(),
+            report.files.iter().map(|file| file.size).sum::<usize>() as f64 / (1024.0 * 1024.0)
+        )?;
+        writeln!(out)?;
+    }
+    table.write(out)?;
+    if results.iter().any(|m| m.results.hit_max_time) {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "\\* --max-time stopped these runs before the throughput was stable."
+        )?;
+    }
+    for file in &report.files {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{} ({:.2} MiB):",
+            file.path,
+            file.size as f64 / (1024.0 * 1024.0)
+        )?;
+        writeln!(out)?;
+        results_table(report, &file.results, file.zero_blocks.as_ref(), stat).write(out)?;
+    }
+
+    let failures: Vec<&Measurement> = results
+        .iter()
+        .filter(|m| m.results.failure.is_some())
+        .collect();
+    if !failures.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "Failed round trips:")?;
+        writeln!(out)?;
+        for measurement in failures {
+            writeln!(
+                out,
+                "- {}: {}",
+                measurement.label(),
+                measurement.results.failure.as_deref().unwrap_or_default()
+            )?;
+        }
+    }
+
+    if let Some(pareto) = &report.pareto {
+        write_pareto(out, pareto)?;
+    }
+    if let Some(costs) = &report.costs {
+        write_costs(out, costs)?;
+    }
+
+    write_memory_table(out, results)?;
+    write_cpu_table(out, results, report.host.cpu_count)?;
+    write_perf_table(out, results)?;
+
+    if let Some(comparison) = &report.comparison {
+        write_comparison(out, comparison)?;
+    }
+
+    if let Some(dictionary) = &report.dictionary {
+        write_dictionary_table(out, dictionary, results)?;
+    }
+
+    if threaded {
+        write_scaling_table(out, results)?;
+    }
+
+    if results.iter().any(|m| m.results.input_sizes.len() > 1) {
+        write_stats_table(out, "Compress", results, |r| r.compress_throughputs())?;
+        write_stats_table(out, "Decompress", results, |r| r.decompress_throughputs())?;
+    }
+    Ok(())
+}
+
+/// Throughput in MiB/s over all of `times`, weighted by size.
+fn total_throughput(total_input_size: f64, times: &[Duration]) -> f64 {
+    let total_time = times.iter().map(|d| d.as_secs_f64()).sum::<f64>();
+    if total_time > 0.0 {
+        (total_input_size / (1024.0 * 1024.0)) / total_time
+    } else {
+        f64::INFINITY
+    }
+}
+
+/// Aggregate (compress, decompress) throughput in MiB/s: total bytes over
+/// total wall time.
+pub fn aggregate_throughput(results: &BenchmarkResults) -> (f64, f64) {
+    let total_input_size = results.input_sizes.iter().sum::<usize>() as f64;
+    (
+        total_throughput(total_input_size, &results.compress_times),
+        total_throughput(total_input_size, &results.decompress_times),
+    )
+}
+
+/// How each codec's aggregate throughput grows with threads: per-thread
+/// throughput, efficiency against its own single-threaded row (`T` threads
+/// at efficiency 1.0 are exactly `T` times faster), and the fraction of memcpy
+/// bandwidth at the same thread count, which shows memory-bound codecs.
+fn write_scaling_table(out: &mut dyn Write, results: &[Measurement]) -> io::Result<()> {
+    let measured =
+        |m: &&Measurement| m.results.failure.is_none() && !m.results.input_sizes.is_empty();
+    let find = |algorithm: &str, threads: usize| {
+        results
+            .iter()
+            .filter(measured)
+            .find(|m| m.algorithm == algorithm && m.threads == threads)
+            .map(|m| aggregate_throughput(&m.results))
+    };
+
+    let mut table = Table::new([
+        "Algorithm",
+        "Threads",
+        "Compress (MiB/s)",
+        "per Thread",
+        "Efficiency",
+        "% memcpy",
+        "Decompress (MiB/s)",
+        "per Thread",
+        "Efficiency",
+        "% memcpy",
+    ]);
+    for measurement in results.iter().filter(measured) {
+        let threads = measurement.threads;
+        let (compress, decompress) = aggregate_throughput(&measurement.results);
+        let single = find(&measurement.algorithm, 1);
+        let memcpy = find("memcpy", threads);
+        let fmt = |value: Option<f64>, suffix: &str| {
+            value.map_or("-".to_string(), |v| format!("{:.2}{}", v, suffix))
+        };
+
+        table.row(vec![
+            measurement.algorithm.clone(),
+            threads.to_string(),
+            format!("{:.2}", compress),
+            format!("{:.2}", compress / threads as f64),
+            fmt(single.map(|(c, _)| compress / (c * threads as f64)), ""),
+            fmt(memcpy.map(|(c, _)| 100.0 * compress / c), "%"),
+            format!("{:.2}", decompress),
+            format!("{:.2}", decompress / threads as f64),
+            fmt(single.map(|(_, d)| decompress / (d * threads as f64)), ""),
+            fmt(memcpy.map(|(_, d)| 100.0 * decompress / d), "%"),
+        ]);
+    }
+
+    writeln!(out)?;
+    writeln!(out, "Thread scaling (aggregate throughput):")?;
+    writeln!(out)?;
+    table.write(out)
+}
+
+/// A byte count in the largest binary unit that keeps it at or above 1.
+fn human_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Per codec and direction, the most Rust heap a pass held beyond the
+/// harness's buffers, the most the RSS grew (which also covers the C
+/// libraries' `malloc`), and what was allocated per pass.
+fn write_memory_table(out: &mut dyn Write, results: &[Measurement]) -> io::Result<()> {
+    let mut table = Table::new([
+        "Algorithm",
+        "Compress Peak Heap",
+        "RSS Growth",
+        "Allocated",
+        "Allocs",
+        "Decompress Peak Heap",
+        "RSS Growth",
+        "Allocated",
+        "Allocs",
+    ]);
+    let measured = results
+        .iter()
+        .filter(|m| m.results.failure.is_none() && m.results.compress_memory.passes > 0);
+    for measurement in measured {
+        let mut row = vec![measurement.label()];
+        for usage in [
+            &measurement.results.compress_memory,
+            &measurement.results.decompress_memory,
+        ] {
+            row.extend([
+                human_bytes(usage.peak_heap as f64),
+                usage
+                    .rss_growth
+                    .map_or("-".to_string(), |rss| human_bytes(rss as f64)),
+                human_bytes(usage.allocated_per_pass()),
+                format!("{:.1}", usage.allocations_per_pass()),
+            ]);
+        }
+        table.row(row);
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Memory per untimed warm-up pass (Allocated and Allocs average over batches):"
+    )?;
+    writeln!(out)?;
+    table.write(out)
+}
+
+/// Per codec and direction, hardware counters per input byte over the timed
+/// runs. Left out without the `perf` feature; with it, a note takes its place
+/// when the host exposed no counters.
+fn write_perf_table(out: &mut dyn Write, results: &[Measurement]) -> io::Result<()> {
+    let mut table = Table::new([
+        "Algorithm",
+        "Direction",
+        "Cycles/Byte",
+        "Instr./Byte",
+        "IPC",
+        "LLC Misses/KiB",
+    ]);
+    let mut counted = false;
+    let measured = results
+        .iter()
+        .filter(|m| m.results.failure.is_none() && !m.results.input_sizes.is_empty());
+    for measurement in measured {
+        let runs = &measurement.results;
+        let bytes = runs.input_sizes.iter().sum::<usize>() as f64;
+        for (direction, counts) in [
+            ("compress", &runs.compress_perf),
+            ("decompress", &runs.decompress_perf),
+        ] {
+            let Some(counts) = counts else {
+                continue;
+            };
+            counted = true;
+            table.row(vec![
+                measurement.label(),
+                direction.to_string(),
+                format!("{:.2}", counts.cycles as f64 / bytes),
+                format!("{:.2}", counts.instructions as f64 / bytes),
+                format!("{:.2}", counts.ipc()),
+                counts.llc_misses.map_or("-".to_string(), |misses| {
+                    format!("{:.2}", misses as f64 / (bytes / 1024.0))
+                }),
+            ]);
+        }
+    }
+
+    if !counted {
+        if cfg!(feature = "perf") {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "Hardware counters unavailable: perf_event_open was refused, as inside most \
+                 VMs or with a restrictive kernel.perf_event_paranoid."
+            )?;
+        }
+        return Ok(());
+    }
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Hardware counters over the timed runs (user space, whole process):"
+    )?;
+    writeln!(out)?;
+    table.write(out)
+}
+
+/// Each codec's place on the ratio/compress and ratio/decompress frontiers,
+/// naming what dominates the rows off a frontier, then the recommendation
+/// under `--require`.
+fn write_pareto(out: &mut dyn Write, pareto: &Pareto) -> io::Result<()> {
+    if pareto.codecs.len() > 1 {
+        let mut table = Table::new([
+            "Algorithm",
+            "Ratio",
+            "Compress (MiB/s)",
+            "Compress Frontier",
+            "Decompress (MiB/s)",
+            "Decompress Frontier",
+        ]);
+        let place = |dominated_by: &Option<String>| match dominated_by {
+            Some(other) => format!("dominated by {}", other),
+            None => "yes".to_string(),
+        };
+        for codec in &pareto.codecs {
+            table.row(vec![
+                codec.label.clone(),
+                format!("{:.2}", codec.ratio),
+                format!("{:.2}", codec.compress),
+                place(&codec.compress_dominated_by),
+                format!("{:.2}", codec.decompress),
+                place(&codec.decompress_dominated_by),
+            ]);
+        }
+        writeln!(out)?;
+        writeln!(
+            out,
+            "Pareto frontiers (a dominated row has another with at least its ratio and speed):"
+        )?;
+        writeln!(out)?;
+        table.write(out)?;
+    }
+
+    if pareto.requirements.is_empty() {
+        return Ok(());
+    }
+    let requirements: Vec<String> = pareto.requirements.iter().map(|r| r.to_string()).collect();
+    writeln!(out)?;
+    match &pareto.recommendation {
+        Some(label) => {
+            let codec = pareto.codecs.iter().find(|c| &c.label == label);
+            write!(
+                out,
+                "Recommended under {}: {}",
+                requirements.join(", "),
+                label
+            )?;
+            if let Some(codec) = codec {
+                write!(
+                    out,
+                    " (ratio {:.2}, compress {:.2} MiB/s, decompress {:.2} MiB/s)",
+                    codec.ratio, codec.compress, codec.decompress
+                )?;
+            }
+            writeln!(out)?;
+        }
+        None => writeln!(out, "No algorithm meets {}.", requirements.join(", "))?,
+    }
+    Ok(())
+}
+
+/// Algorithms from cheapest to dearest under the cost model, per TiB of
+/// uncompressed data, with the total as a share of storing it uncompressed.
+fn write_costs(out: &mut dyn Write, costs: &CostReport) -> io::Result<()> {
+    if costs.codecs.is_empty() {
+        return Ok(());
+    }
+    let model = &costs.model;
+    let uncompressed = model.uncompressed();
+    let mut table = Table::new([
+        "Algorithm",
+        "Storage ($)",
+        "Compress CPU ($)",
+        "Decompress CPU ($)",
+        "Total ($)",
+        "vs. Uncompressed",
+    ]);
+    for codec in &costs.codecs {
+        table.row(vec![
+            codec.label.clone(),
+            format!("{:.2}", codec.storage),
+            format!("{:.4}", codec.compress_cpu),
+            format!("{:.4}", codec.decompress_cpu),
+            format!("{:.2}", codec.total),
+            format!("{:.1}%", codec.total / uncompressed * 100.0),
+        ]);
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Projected cost per TiB of uncompressed data, cheapest first: ${}/GiB-month for {} \
+         months, ${}/core-hour, {} reads per stripe (${:.2} stored uncompressed):",
+        model.storage_per_gib_month,
+        model.retention_months,
+        model.cpu_per_core_hour,
+        model.reads,
+        uncompressed
+    )?;
+    if costs.skipped_zero_blocks {
+        writeln!(
+            out,
+            "Skipped all-zero blocks are free to store and process: storage uses Eff. Ratio \
+             and CPU time shrinks in proportion."
+        )?;
+    }
+    writeln!(out)?;
+    table.write(out)
+}
+
+/// Per codec, ratio and aggregate throughput against the baseline, whether
+/// each throughput change is significant, then the regressions and the
+/// slowdowns too few runs could not confirm.
+fn write_comparison(out: &mut dyn Write, comparison: &Comparison) -> io::Result<()> {
+    let mut table = Table::new([
+        "Algorithm",
+        "Ratio",
+        "Change",
+        "Compress (MiB/s)",
+        "Change",
+        "Significant",
+        "Decompress (MiB/s)",
+        "Change",
+        "Significant",
+    ]);
+    for codec in &comparison.codecs {
+        let mut row = vec![
+            codec.label(),
+            format!("{:.2}", codec.ratio),
+            if codec.ratio > 0.0 && codec.baseline_ratio > 0.0 {
+                format!(
+                    "{:+.1}%",
+                    (codec.ratio / codec.baseline_ratio - 1.0) * 100.0
+                )
+            } else {
+                "-".to_string()
+            },
+        ];
+        for throughput in [&codec.compress, &codec.decompress] {
+            match throughput {
+                Some(throughput) => row.extend([
+                    format!("{:.2}", throughput.current),
+                    format!("{:+.1}%", throughput.change * 100.0),
+                    match throughput.significant {
+                        Some(true) => "yes".to_string(),
+                        Some(false) => "no".to_string(),
+                        None => "-".to_string(),
+                    },
+                ]),
+                None => row.extend(["FAILED".to_string(), "-".to_string(), "-".to_string()]),
+            }
+        }
+        table.row(row);
+    }
+
+    writeln!(out)?;
+    writeln!(out, "Compared with baseline {}:", comparison.baseline)?;
+    writeln!(out)?;
+    if let Some(input) = &comparison.baseline_input {
+        writeln!(out, "The baseline was measured on {}.", input)?;
+    }
+    if let Some(chunk_size) = comparison.baseline_chunk_size {
+        writeln!(out, "The baseline used chunks of {} bytes.", chunk_size)?;
+    }
+    if comparison.baseline_input.is_some() || comparison.baseline_chunk_size.is_some() {
+        writeln!(out)?;
+    }
+    table.write(out)?;
+    for (what, labels) in [
+        ("Not in the baseline", &comparison.added),
+        ("Only in the baseline", &comparison.removed),
+    ] {
+        if !labels.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "{}: {}", what, labels.join(", "))?;
+        }
+    }
+
+    let regressions: Vec<_> = comparison.regressions().collect();
+    writeln!(out)?;
+    if regressions.is_empty() {
+        writeln!(
+            out,
+            "No regressions beyond {:.1}%.",
+            comparison.threshold * 100.0
+        )?;
+    } else {
+        writeln!(
+            out,
+            "Regressions beyond {:.1}%:",
+            comparison.threshold * 100.0
+        )?;
+        writeln!(out)?;
+        for codec in regressions {
+            writeln!(
+                out,
+                "- {}: {}",
+                codec.label(),
+                codec.regression.as_deref().unwrap_or_default()
+            )?;
+        }
+    }
+
+    let unconfirmed: Vec<_> = comparison.unconfirmed().collect();
+    if !unconfirmed.is_empty() {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "Slower by more than {:.1}%, but with too few runs to tell from noise \
+             (use --runs or --min-time for at least two):",
+            comparison.threshold * 100.0
+        )?;
+        writeln!(out)?;
+        for codec in unconfirmed {
+            writeln!(
+                out,
+                "- {}: {}",
+                codec.label(),
+                codec.unconfirmed.as_deref().unwrap_or_default()
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Timed sections shorter than this in total are too short for a CPU/wall
+/// comparison to mean anything.
+const MIN_CPU_WALL: Duration = Duration::from_millis(10);
+
+/// Per codec and direction, CPU time against wall time over the timed runs,
+/// context switches and page faults, then a warning for every row whose CPU
+/// time strays from what its thread count predicts on `cpus` CPUs.
+fn write_cpu_table(out: &mut dyn Write, results: &[Measurement], cpus: usize) -> io::Result<()> {
+    let mut table = Table::new([
+        "Algorithm",
+        "Direction",
+        "Wall (s)",
+        "User (s)",
+        "Sys (s)",
+        "CPU / Wall",
+        "Vol. Switches",
+        "Invol. Switches",
+        "Minor Faults",
+        "Major Faults",
+    ]);
+    let mut warnings = Vec::new();
+    let measured = results
+        .iter()
+        .filter(|m| m.results.failure.is_none() && !m.results.input_sizes.is_empty());
+    for measurement in measured {
+        let runs = &measurement.results;
+        for (direction, times, usage) in [
+            ("compress", &runs.compress_times, &runs.compress_cpu),
+            ("decompress", &runs.decompress_times, &runs.decompress_cpu),
+        ] {
+            let wall = times.iter().sum::<Duration>();
+            let cpu_per_wall = usage.cpu_ns as f64 / 1e9 / wall.as_secs_f64();
+            table.row(vec![
+                measurement.label(),
+                direction.to_string(),
+                format!("{:.3}", wall.as_secs_f64()),
+                format!("{:.3}", usage.user_ns as f64 / 1e9),
+                format!("{:.3}", usage.sys_ns as f64 / 1e9),
+                format!("{:.2}", cpu_per_wall),
+                usage.voluntary_switches.to_string(),
+                usage.involuntary_switches.to_string(),
+                usage.minor_faults.to_string(),
+                usage.major_faults.to_string(),
+            ]);
+
+            let expected = measurement.threads.min(cpus.max(1)) as f64;
+            if wall < MIN_CPU_WALL || (cpu_per_wall / expected - 1.0).abs() <= DIVERGENCE_THRESHOLD
+            {
+                continue;
+            }
+            let cause = if cpu_per_wall < expected {
+                "it was preempted or blocked, so its throughput understates the codec"
+            } else {
+                "more threads were busy than it was given, such as codec-internal workers"
+            };
+            warnings.push(format!(
+                "- {} {}: CPU time is {:.2}x wall time on {} thread{}; {}",
+                measurement.label(),
+                direction,
+                cpu_per_wall,
+                measurement.threads,
+                if measurement.threads == 1 { "" } else { "s" },
+                cause
+            ));
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "CPU time over the timed runs (whole process, so codec-internal threads count):"
+    )?;
+    writeln!(out)?;
+    table.write(out)?;
+    if !warnings.is_empty() {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "CPU and wall time differ by more than {:.0}%:",
+            DIVERGENCE_THRESHOLD * 100.0
+        )?;
+        writeln!(out)?;
+        for warning in warnings {
+            writeln!(out, "{}", warning)?;
+        }
+    }
+    Ok(())
+}
+
+/// How the dictionary was trained, and each `zstd (level N, dict)` row's
+/// ratio against the same level without the dictionary.
+fn write_dictionary_table(
+    out: &mut dyn Write,
+    dictionary: &DictionaryTraining,
+    results: &[Measurement],
+) -> io::Result<()> {
+    let measured = |m: &&Measurement| m.results.failure.is_none();
+    let held_out = |algorithm: &str| {
+        dictionary
+            .held_out
+            .iter()
+            .find(|h| h.algorithm == algorithm)
+            .and_then(HeldOut::ratio)
+    };
+    let mut table = Table::new([
+        "Algorithm",
+        "Ratio",
+        "Dict Ratio",
+        "Gain",
+        "Held-out Ratio",
+        "Held-out Dict Ratio",
+        "Held-out Gain",
+    ]);
+    for with_dict in results.iter().filter(measured) {
+        let Some(level) = with_dict.algorithm.strip_suffix(", dict)") else {
+            continue;
+        };
+        let without = format!("{})", level);
+        let Some(plain) = results
+            .iter()
+            .filter(measured)
+            .find(|m| m.algorithm == without && m.threads == with_dict.threads)
+        else {
+            continue;
+        };
+        let (before, after) = (ratio(&plain.results), ratio(&with_dict.results));
+        let mut row = vec![
+            plain.label(),
+            format!("{:.2}", before),
+            format!("{:.2}", after),
+            format!("{:+.1}%", 100.0 * (after / before - 1.0)),
+        ];
+        match (held_out(&plain.algorithm), held_out(&with_dict.algorithm)) {
+            (Some(before), Some(after)) => row.extend([
+                format!("{:.2}", before),
+                format!("{:.2}", after),
+                format!("{:+.1}%", 100.0 * (after / before - 1.0)),
+            ]),
+            _ => row.extend(["-".to_string(), "-".to_string(), "-".to_string()]),
+        }
+        table.row(row);
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "zstd dictionary: {} bytes (of {} requested), trained on {} of {} chunks ({:.2} MiB) in {:.3}ms.",
+        dictionary.size,
+        dictionary.max_size,
+        dictionary.samples,
+        dictionary.chunks,
+        dictionary.sample_bytes as f64 / (1024.0 * 1024.0),
+        dictionary.training_ns as f64 / 1e6
+    )?;
+    writeln!(
+        out,
+        "Ratio and Dict Ratio cover every chunk, including those the dictionary was trained on; \
+         the held-out columns leave them out, along with all-zero chunks."
+    )?;
+    writeln!(out)?;
+    table.write(out)
+}
+
+/// One matrix per format: a row per producer with its ratio and compression
+/// speed, and a column per consumer with its decompression speed on that
+/// producer's output, or `FAILED` where the consumer could not read it.
+fn write_interop_tables(out: &mut dyn Write, pairs: &[InteropPair]) -> io::Result<()> {
+    let mib = |bytes: f64, ns: u64| {
+        if ns > 0 {
+            format!("{:.2}", bytes / (1024.0 * 1024.0) / (ns as f64 / 1e9))
+        } else {
+            "-".to_string()
+        }
+    };
+    let mut formats: Vec<&str> = Vec::new();
+    for pair in pairs {
+        if !formats.contains(&pair.format.as_str()) {
+            formats.push(&pair.format);
+        }
+    }
+
+    for format in formats {
+        let group: Vec<&InteropPair> = pairs.iter().filter(|p| p.format == format).collect();
+        let mut codecs: Vec<&str> = Vec::new();
+        for pair in &group {
+            if !codecs.contains(&pair.producer.as_str()) {
+                codecs.push(&pair.producer);
+            }
+        }
+
+        let mut headers = vec![
+            "Producer \\ Consumer".to_string(),
+            "Ratio".to_string(),
+            "Compress (MiB/s)".to_string(),
+        ];
+        headers.extend(codecs.iter().map(|c| c.to_string()));
+        let mut table = Table::new(headers);
+        for &producer in &codecs {
+            let row_pairs: Vec<&&InteropPair> =
+                group.iter().filter(|p| p.producer == producer).collect();
+            // Every consumer sees the same compressed data
+            let measured = row_pairs.iter().find(|p| p.input_size > 0);
+            let mut row = vec![
+                producer.to_string(),
+                measured.map_or("-".to_string(), |p| {
+                    format!("{:.2}", p.input_size as f64 / p.compressed_size as f64)
+                }),
+                measured.map_or("-".to_string(), |p| {
+                    mib((p.input_size * p.runs) as f64, p.compress_ns)
+                }),
+            ];
+            for &consumer in &codecs {
+                let cell = match row_pairs.iter().find(|p| p.consumer == consumer) {
+                    Some(p) if p.failure.is_some() => "FAILED".to_string(),
+                    Some(p) => mib((p.input_size * p.runs) as f64, p.decompress_ns),
+                    None => "-".to_string(),
+                };
+                row.push(cell);
+            }
+            table.row(row);
+        }
+
+        writeln!(out)?;
+        writeln!(out, "{} (decompress MiB/s by consumer):", format)?;
+        writeln!(out)?;
+        table.write(out)?;
+    }
+
+    let failures: Vec<&InteropPair> = pairs.iter().filter(|p| p.failure.is_some()).collect();
+    if !failures.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "Incompatible pairs:")?;
+        writeln!(out)?;
+        for pair in failures {
+            writeln!(
+                out,
+                "- {} -> {}: {}",
+                pair.producer,
+                pair.consumer,
+                pair.failure.as_deref().unwrap_or_default()
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// One row per decoder with the ratio of the files it read and its
+/// decompression speed, then the files that were skipped and the files each
+/// decoder failed on.
+fn write_decode_markdown(out: &mut dyn Write, report: &DecodeReport, stat: Stat) -> io::Result<()> {
+    let decoded: Vec<&DecodeInput> = report
+        .inputs
+        .iter()
+        .filter(|input| input.skipped.is_none())
+        .collect();
+    let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    let decompress_header = match stat {
+        Stat::Total => "Decompress (MiB/s)".to_string(),
+        stat => format!("Decompress {} (MiB/s)", stat.label()),
+    };
+    let mut table = Table::new([
+        "Decoder".to_string(),
+        "Format".to_string(),
+        "Files".to_string(),
+        "Output (MiB)".to_string(),
+        "Ratio".to_string(),
+        decompress_header,
+    ]);
+    for measurement in &report.results {
+        let mut row = vec![measurement.decoder.clone(), measurement.format.clone()];
+        if measurement.files == 0 {
+            if !measurement.failures.is_empty() {
+                row.resize(6, "FAILED".to_string());
+                table.row(row);
+            }
+            continue;
+        }
+        let times: Vec<Duration> = measurement
+            .decompress_ns
+            .iter()
+            .map(|&ns| Duration::from_nanos(ns))
+            .collect();
+        let sizes = vec![measurement.decompressed_size; times.len()];
+        let throughput = stats::summarize(&crate::harness::throughputs(&sizes, &times))
+            .and_then(|summary| stat.pick(&summary))
+            .unwrap_or_else(|| {
+                total_throughput((measurement.decompressed_size * times.len()) as f64, &times)
+            });
+        row.extend([
+            measurement.files.to_string(),
+            format!("{:.2}", mib(measurement.decompressed_size)),
+            format!(
+                "{:.2}",
+                measurement.decompressed_size as f64 / measurement.compressed_size as f64
+            ),
+            format!("{:.2}", throughput),
+        ]);
+        table.row(row);
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Decoded {} files: {:.2} MiB compressed, {:.2} MiB decompressed.",
+        decoded.len(),
+        mib(decoded.iter().map(|input| input.compressed_size).sum()),
+        mib(decoded.iter().map(|input| input.decompressed_size).sum())
+    )?;
+    writeln!(out)?;
+    table.write(out)?;
+
+    let skipped: Vec<&DecodeInput> = report
+        .inputs
+        .iter()
+        .filter(|input| input.skipped.is_some())
+        .collect();
+    if !skipped.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "Skipped files:")?;
+        writeln!(out)?;
+        for input in skipped {
+            writeln!(
+                out,
+                "- {}: {}",
+                input.path,
+                input.skipped.as_deref().unwrap_or_default()
+            )?;
+        }
+    }
+
+    if report.results.iter().any(|m| !m.failures.is_empty()) {
+        writeln!(out)?;
+        writeln!(out, "Failed decodes:")?;
+        writeln!(out)?;
+        for measurement in &report.results {
+            for failure in &measurement.failures {
+                writeln!(out, "- {}: {}", measurement.decoder, failure)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Total input size over total compressed size.
+pub fn ratio(results: &BenchmarkResults) -> f64 {
+    let total_input_size = results.input_sizes.iter().sum::<usize>() as f64;
+    let total_compressed_size = results.compressed_sizes.iter().sum::<usize>() as f64;
+    if total_compressed_size > 0.0 {
+        total_input_size / total_compressed_size
+    } else {
+        0.0
+    }
+}
+
+fn markdown_row(
+    zero_blocks: Option<&ZeroBlockSkip>,
+    measurement: &Measurement,
+    stat: Stat,
+) -> Option<Vec<String>> {
+    let name = &measurement.algorithm;
+    let results = &measurement.results;
+    let columns = if zero_blocks.is_some() { 5 } else { 4 };
+
+    // A codec that broke the round trip gets no score at all
+    if results.failure.is_some() {
+        let mut row = vec![name.clone()];
+        row.resize(columns, "FAILED".to_string());
+        return Some(row);
+    }
+
+    // Guard against empty results
+    if results.input_sizes.is_empty()
+        || results.compressed_sizes.is_empty()
+        || results.compress_times.is_empty()
+        || results.decompress_times.is_empty()
+    {
+        eprintln!("Warning: No results to display for {}", name);
+        return None;
+    }
+
+    let total_input_size = results.input_sizes.iter().sum::<usize>() as f64;
+    let ratio = ratio(results);
+
+    let compress_throughput = stats::summarize(&results.compress_throughputs())
+        .and_then(|summary| stat.pick(&summary))
+        .unwrap_or_else(|| total_throughput(total_input_size, &results.compress_times));
+    let decompress_throughput = stats::summarize(&results.decompress_throughputs())
+        .and_then(|summary| stat.pick(&summary))
+        .unwrap_or_else(|| total_throughput(total_input_size, &results.decompress_times));
+
+    let mut row = vec![name.clone(), format!("{:.2}", ratio)];
+    if let Some(zero_blocks) = zero_blocks {
+        row.push(format!("{:.2}", ratio * zero_blocks.scale()));
+    }
+    row.push(format!("{:.2}", compress_throughput));
+    row.push(format!("{:.2}", decompress_throughput));
+    Some(row)
+}
+
+/// Per-codec distribution of one direction's per-run throughput.
+fn write_stats_table(
+    out: &mut dyn Write,
+    direction: &str,
+    results: &[Measurement],
+    samples: impl Fn(&BenchmarkResults) -> Vec<f64>,
+) -> io::Result<()> {
+    let mut table = Table::new([
+        "Algorithm",
+        "Runs",
+        "Min",
+        "Median",
+        "Mean",
+        "Stddev",
+        "P95",
+        "P99",
+        "95% CI of mean",
+    ]);
+    for measurement in results.iter().filter(|m| m.results.failure.is_none()) {
+        let Some(summary) = stats::summarize(&samples(&measurement.results)) else {
+            continue;
+        };
+        table.row(vec![
+            measurement.label(),
+            measurement.results.input_sizes.len().to_string(),
+            format!("{:.2}", summary.min),
+            format!("{:.2}", summary.median),
+            format!("{:.2}", summary.mean),
+            format!("{:.2}", summary.stddev),
+            format!("{:.2}", summary.p95),
+            format!("{:.2}", summary.p99),
+            format!("{:.2} .. {:.2}", summary.ci_low, summary.ci_high),
+        ]);
+    }
+
+    writeln!(out)?;
+    writeln!(out, "{} throughput per run (MiB/s):", direction)?;
+    writeln!(out)?;
+    table.write(out)
+}
+
+pub fn write_json(out: &mut dyn Write, report: &Report) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, report)?;
+    writeln!(out)
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote or newline.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// One row per run. Host metadata goes in leading `#` comment lines, which
+/// most CSV readers can skip (e.g. pandas' `comment='#'`).
+pub fn write_csv(out: &mut dyn Write, report: &Report) -> io::Result<()> {
+    let host = &report.host;
+    writeln!(out, "# input: {}", report.input)?;
+    writeln!(out, "# chunk_size: {}", report.chunk_size)?;
+    writeln!(out, "# num_runs: {}", report.num_runs)?;
+    if let Some(budget) = &report.budget {
+        writeln!(out, "# min_time_ns: {}", budget.min_time.as_nanos())?;
+        writeln!(out, "# max_time_ns: {}", budget.max_time.as_nanos())?;
+        writeln!(out, "# target_stderr: {}", budget.target_stderr)?;
+    }
+    writeln!(out, "# cpu_model: {}", host.cpu_model)?;
+    writeln!(out, "# cpu_count: {}", host.cpu_count)?;
+    writeln!(out, "# kernel: {}", host.kernel)?;
+    writeln!(out, "# rustc: {}", host.rustc)?;
+    for (name, version) in &host.crates {
+        writeln!(out, "# crate: {} {}", name, version)?;
+    }
+    if let Some(zero_blocks) = &report.zero_blocks {
+        writeln!(out, "# zero_block_size: {}", zero_blocks.block_size)?;
+        writeln!(out, "# zero_blocks_read: {}", zero_blocks.blocks_read)?;
+        writeln!(out, "# zero_blocks_skipped: {}", zero_blocks.blocks_skipped)?;
+        writeln!(out, "# zero_bytes_read: {}", zero_blocks.bytes_read)?;
+        writeln!(out, "# zero_bytes_skipped: {}", zero_blocks.bytes_skipped)?;
+    }
+    if let Some(io_buffer) = report.io_buffer {
+        writeln!(out, "# io_buffer: {}", io_buffer)?;
+    }
+    if let Some(stability) = &report.stability {
+        if let Some(cpu) = stability.pinned_cpu {
+            writeln!(out, "# pinned_cpu: {}", cpu)?;
+        }
+        writeln!(out, "# spin_ms: {}", stability.spin_ms)?;
+        writeln!(out, "# memcpy_before_mib_s: {:.2}", stability.memcpy_before)?;
+        writeln!(out, "# memcpy_after_mib_s: {:.2}", stability.memcpy_after)?;
+    }
+    if let Some(dictionary) = &report.dictionary {
+        writeln!(out, "# zstd_dict_max_size: {}", dictionary.max_size)?;
+        writeln!(out, "# zstd_dict_size: {}", dictionary.size)?;
+        writeln!(out, "# zstd_dict_chunks: {}", dictionary.chunks)?;
+        writeln!(out, "# zstd_dict_samples: {}", dictionary.samples)?;
+        writeln!(out, "# zstd_dict_sample_bytes: {}", dictionary.sample_bytes)?;
+        writeln!(out, "# zstd_dict_training_ns: {}", dictionary.training_ns)?;
+    }
+    for measurement in &report.results {
+        if let Some(failure) = &measurement.results.failure {
+            writeln!(out, "# failed: {}: {}", measurement.label(), failure)?;
+        }
+    }
+    if let Some(comparison) = &report.comparison {
+        writeln!(out, "# baseline: {}", comparison.baseline)?;
+        for codec in comparison.regressions() {
+            writeln!(
+                out,
+                "# regression: {}: {}",
+                codec.label(),
+                codec.regression.as_deref().unwrap_or_default()
+            )?;
+        }
+        for codec in comparison.unconfirmed() {
+            writeln!(
+                out,
+                "# unconfirmed: {}: {}",
+                codec.label(),
+                codec.unconfirmed.as_deref().unwrap_or_default()
+            )?;
+        }
+    }
+
+    if !report.interop.is_empty() {
+        return write_interop_csv(out, &report.interop);
+    }
+
+    writeln!(
+        out,
+        "algorithm,threads,run,input_size,compressed_size,compress_ns,decompress_ns,\
+         compress_peak_heap,compress_allocated_bytes,compress_allocations,compress_peak_rss,\
+         compress_rss_growth,decompress_peak_heap,decompress_allocated_bytes,\
+         decompress_allocations,decompress_peak_rss,decompress_rss_growth,\
+         compress_cpu_ns,compress_user_ns,compress_sys_ns,compress_voluntary_switches,\
+         compress_involuntary_switches,compress_minor_faults,compress_major_faults,\
+         decompress_cpu_ns,decompress_user_ns,decompress_sys_ns,decompress_voluntary_switches,\
+         decompress_involuntary_switches,decompress_minor_faults,decompress_major_faults,\
+         compress_cycles,compress_instructions,compress_llc_misses,\
+         decompress_cycles,decompress_instructions,decompress_llc_misses,file"
+    )?;
+    // Memory, CPU usage and counters are totalled per codec (and file), so every run of
+    // a codec repeats them
+    let memory = |usage: &MemoryUsage| {
+        format!(
+            "{},{},{},{},{}",
+            usage.peak_heap,
+            usage.allocated_bytes,
+            usage.allocations,
+            usage.peak_rss.map_or(String::new(), |rss| rss.to_string()),
+            usage
+                .rss_growth
+                .map_or(String::new(), |rss| rss.to_string())
+        )
+    };
+    let cpu = |usage: &CpuUsage| {
+        format!(
+            "{},{},{},{},{},{},{}",
+            usage.cpu_ns,
+            usage.user_ns,
+            usage.sys_ns,
+            usage.voluntary_switches,
+            usage.involuntary_switches,
+            usage.minor_faults,
+            usage.major_faults
+        )
+    };
+    let counters = |counts: &Option<PerfCounts>| match counts {
+        Some(counts) => format!(
+            "{},{},{}",
+            counts.cycles,
+            counts.instructions,
+            counts
+                .llc_misses
+                .map_or(String::new(), |misses| misses.to_string())
+        ),
+        None => ",,".to_string(),
+    };
+    // A corpus is written file by file, so that each file's runs carry its
+    // own totals
+    let inputs: Vec<(&str, &[Measurement])> = if report.files.is_empty() {
+        vec![(&report.input, &report.results)]
+    } else {
+        report
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.results.as_slice()))
+            .collect()
+    };
+    for (input, measurement) in inputs
+        .iter()
+        .flat_map(|&(input, results)| results.iter().map(move |m| (csv_field(input), m)))
+    {
+        let name = csv_field(&measurement.algorithm);
+        let runs = &measurement.results;
+        let usage = [
+            memory(&runs.compress_memory),
+            memory(&runs.decompress_memory),
+            cpu(&runs.compress_cpu),
+            cpu(&runs.decompress_cpu),
+            counters(&runs.compress_perf),
+            counters(&runs.decompress_perf),
+        ]
+        .join(",");
+        for run in 0..runs.input_sizes.len() {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{}",
+                name,
+                measurement.threads,
+                run + 1,
+                runs.input_sizes[run],
+                runs.compressed_sizes[run],
+                runs.compress_times[run].as_nanos(),
+                runs.decompress_times[run].as_nanos(),
+                usage,
+                input
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// One row per producer/consumer pair, with an empty `failure` when the pair
+/// round-tripped.
+fn write_interop_csv(out: &mut dyn Write, pairs: &[InteropPair]) -> io::Result<()> {
+    writeln!(
+        out,
+        "format,producer,consumer,input_size,compressed_size,compress_ns,decompress_ns,runs,failure"
+    )?;
+    for pair in pairs {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{}",
+            csv_field(&pair.format),
+            csv_field(&pair.producer),
+            csv_field(&pair.consumer),
+            pair.input_size,
+            pair.compressed_size,
+            pair.compress_ns,
+            pair.decompress_ns,
+            pair.runs,
+            csv_field(pair.failure.as_deref().unwrap_or_default())
+        )?;
+    }
+    Ok(())
+}
+
+/// One row per decoder and run, with the run's time over every file of the
+/// decoder's format. Skipped files and failed decodes go in `#` lines.
+fn write_decode_csv(out: &mut dyn Write, report: &DecodeReport) -> io::Result<()> {
+    let host = &report.host;
+    for input in &report.inputs {
+        match (&input.format, &input.skipped) {
+            (_, Some(reason)) => writeln!(out, "# skipped: {}: {}", input.path, reason)?,
+            (Some(format), None) => writeln!(out, "# input: {} ({})", input.path, format)?,
+            (None, None) => writeln!(out, "# input: {}", input.path)?,
+        }
+    }
+    writeln!(out, "# num_runs: {}", report.num_runs)?;
+    writeln!(out, "# cpu_model: {}", host.cpu_model)?;
+    writeln!(out, "# cpu_count: {}", host.cpu_count)?;
+    writeln!(out, "# kernel: {}", host.kernel)?;
+    writeln!(out, "# rustc: {}", host.rustc)?;
+    for (name, version) in &host.crates {
+        writeln!(out, "# crate: {} {}", name, version)?;
+    }
+    for measurement in &report.results {
+        for failure in &measurement.failures {
+            writeln!(out, "# failed: {}: {}", measurement.decoder, failure)?;
+        }
+    }
+
+    writeln!(
+        out,
+        "format,decoder,run,files,compressed_size,decompressed_size,decompress_ns"
+    )?;
+    for measurement in &report.results {
+        for (run, ns) in measurement.decompress_ns.iter().enumerate() {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{}",
+                csv_field(&measurement.format),
+                csv_field(&measurement.decoder),
+                run + 1,
+                measurement.files,
+                measurement.compressed_size,
+                measurement.decompressed_size,
+                ns
+            )?;
+        }
+    }
+    Ok(())
+}
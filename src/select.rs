@@ -0,0 +1,112 @@
+//! Choosing which codecs to run from `--algorithms` / `--exclude` patterns.
+
+use crate::codecs::CodecSpec;
+
+/// Matches `name` against a shell-style pattern where `*` matches any run of
+/// characters and `?` matches exactly one.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` seen and the name index it was tried at.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, n));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Keeps the codecs matching any `include` pattern (all of them if `include`
+/// is empty) and none of the `exclude` patterns.
+///
+/// Fails if an `include` pattern matches no codec at all, which is almost
+/// always a typo.
+pub fn filter(
+    codecs: Vec<CodecSpec>,
+    include: &[String],
+    exclude: &[String],
+) -> Result<Vec<CodecSpec>, String> {
+    for pattern in include {
+        if !codecs.iter().any(|c| glob_match(pattern, c.name())) {
+            return Err(format!(
+                "'{}' does not match any algorithm (see --list-algorithms)",
+                pattern
+            ));
+        }
+    }
+
+    Ok(codecs
+        .into_iter()
+        .filter(|c| include.is_empty() || include.iter().any(|p| glob_match(p, c.name())))
+        .filter(|c| !exclude.iter().any(|p| glob_match(p, c.name())))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn literal_patterns_match_whole_names() {
+        assert!(glob_match("lz4", "lz4"));
+        assert!(!glob_match("lz4", "lz4_flex"));
+        assert!(!glob_match("lz4_flex", "lz4"));
+        assert!(glob_match("zstd (level 3)", "zstd (level 3)"));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(glob_match("zstd*", "zstd (level 3)"));
+        assert!(glob_match("*stream*", "xz2 (lzma, stream)"));
+        assert!(glob_match("lz4?flex", "lz4_flex"));
+        assert!(!glob_match("lz4?flex", "lz4flex"));
+        assert!(glob_match("*(level ?)", "zstd (level 1)"));
+        assert!(!glob_match("*(level ?)", "zstd (level 10)"));
+        // Backtracks past an early match of the text after the star
+        assert!(glob_match("*a*b", "aXaYb"));
+        assert!(!glob_match("*a*b", "aXaYbc"));
+    }
+
+    #[test]
+    fn empty_pattern_or_name() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "lz4"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("?", ""));
+        assert!(!glob_match("lz4", ""));
+    }
+
+    #[test]
+    fn patterns_longer_than_the_name() {
+        assert!(!glob_match("lz4 (frame, checksum)", "lz4"));
+        assert!(!glob_match("lz4?", "lz4"));
+        assert!(!glob_match("lz4*x", "lz4"));
+        assert!(glob_match("lz4***", "lz4"));
+        assert!(glob_match("*lz4*", "lz4"));
+    }
+
+    #[test]
+    fn non_ascii_characters_are_single_characters() {
+        assert!(glob_match("caf?", "café"));
+        assert!(!glob_match("caf??", "café"));
+    }
+}
@@ -0,0 +1,76 @@
+//! Controls for reproducible runs on shared or thermally limited hosts: CPU
+//! pinning, a busy spin before the first measurement so the clock settles,
+//! and a memcpy bandwidth check at the start and the end of the benchmark.
+
+use std::hint::black_box;
+use std::io;
+use std::mem::MaybeUninit;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Pins the calling thread, and every thread it starts afterwards, to `cpu`.
+pub fn pin_cpu(cpu: usize) -> io::Result<()> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("CPU {} is out of range", cpu),
+        ));
+    }
+    // SAFETY: an all-zero cpu_set_t is an empty set, `cpu` is in range, and
+    // sched_setaffinity only reads the set
+    unsafe {
+        let mut set = MaybeUninit::<libc::cpu_set_t>::zeroed().assume_init();
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            let error = io::Error::last_os_error();
+            if error.raw_os_error() == Some(libc::EINVAL) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("CPU {} is not available to this process", cpu),
+                ));
+            }
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// Keeps the CPU busy for `duration`, so that frequency scaling has ramped
+/// up (or a turbo budget has settled) before the first measurement.
+pub fn spin(duration: Duration) {
+    let start = Instant::now();
+    let mut state = 0u64;
+    while start.elapsed() < duration {
+        for _ in 0..10_000 {
+            state = black_box(state.wrapping_mul(6364136223846793005).wrapping_add(1));
+        }
+    }
+}
+
+/// The settings used for a run and the memcpy bandwidth measured before and
+/// after it.
+#[derive(Serialize, Deserialize)]
+pub struct Stability {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_cpu: Option<usize>,
+    pub spin_ms: u64,
+    /// memcpy bandwidth in MiB/s right before the first codec ran.
+    pub memcpy_before: f64,
+    /// memcpy bandwidth in MiB/s after the last codec finished.
+    pub memcpy_after: f64,
+    /// Relative drift beyond which the report warns.
+    pub drift_threshold: f64,
+}
+
+impl Stability {
+    /// Relative change of memcpy bandwidth from start to end; negative when
+    /// the host got slower.
+    pub fn drift(&self) -> f64 {
+        self.memcpy_after / self.memcpy_before - 1.0
+    }
+
+    pub fn drifted(&self) -> bool {
+        self.drift().abs() > self.drift_threshold
+    }
+}
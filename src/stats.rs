@@ -0,0 +1,224 @@
+//! Summary statistics over per-run throughput.
+
+use crate::rng::SplitMix64;
+
+/// Bootstrap resamples used for the confidence interval.
+const BOOTSTRAP_RESAMPLES: usize = 1000;
+/// Fixed so that the same runs always produce the same interval.
+const BOOTSTRAP_SEED: u64 = 0x5eed;
+
+/// Which throughput figure the results table shows.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    /// Total bytes over total time, i.e. a size-weighted mean.
+    Total,
+    Min,
+    Max,
+    Mean,
+    Median,
+    P95,
+    P99,
+}
+
+impl Stat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "total" => Some(Stat::Total),
+            "min" => Some(Stat::Min),
+            "max" => Some(Stat::Max),
+            "mean" => Some(Stat::Mean),
+            "median" => Some(Stat::Median),
+            "p95" => Some(Stat::P95),
+            "p99" => Some(Stat::P99),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Stat::Total => "total",
+            Stat::Min => "min",
+            Stat::Max => "max",
+            Stat::Mean => "mean",
+            Stat::Median => "median",
+            Stat::P95 => "p95",
+            Stat::P99 => "p99",
+        }
+    }
+
+    /// The statistic taken from `summary`, or `None` for [`Stat::Total`],
+    /// which needs the raw sizes and times.
+    pub fn pick(self, summary: &Summary) -> Option<f64> {
+        match self {
+            Stat::Total => None,
+            Stat::Min => Some(summary.min),
+            Stat::Max => Some(summary.max),
+            Stat::Mean => Some(summary.mean),
+            Stat::Median => Some(summary.median),
+            Stat::P95 => Some(summary.p95),
+            Stat::P99 => Some(summary.p99),
+        }
+    }
+}
+
+/// Distribution of per-run throughput samples.
+///
+/// `p95` and `p99` are taken from the slow tail: 95% (99%) of runs were at
+/// least this fast. `ci_low`/`ci_high` bound the mean at 95% confidence by
+/// percentile bootstrap.
+pub struct Summary {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub stddev: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub ci_low: f64,
+    pub ci_high: f64,
+}
+
+/// Linearly interpolated percentile of already sorted `sorted`, `q` in 0..=1.
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let rank = q * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+fn mean(samples: &[f64]) -> f64 {
+    samples.iter().sum::<f64>() / samples.len() as f64
+}
+
+/// Standard error of the mean of `samples` relative to the mean, or `None`
+/// with fewer than two samples.
+pub fn relative_stderr(samples: &[f64]) -> Option<f64> {
+    if samples.len() < 2 {
+        return None;
+    }
+    let mean = mean(samples);
+    let variance =
+        samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (samples.len() - 1) as f64;
+    Some(variance.sqrt() / (samples.len() as f64).sqrt() / mean)
+}
+
+/// Summarizes `samples`, or returns `None` if there are none.
+pub fn summarize(samples: &[f64]) -> Option<Summary> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let mean = mean(samples);
+    // Sample (n - 1) standard deviation; zero for a single run.
+    let stddev = if samples.len() > 1 {
+        let variance =
+            samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (samples.len() - 1) as f64;
+        variance.sqrt()
+    } else {
+        0.0
+    };
+
+    let mut rng = SplitMix64::new(BOOTSTRAP_SEED);
+    let mut resample = vec![0.0; samples.len()];
+    let mut means: Vec<f64> = (0..BOOTSTRAP_RESAMPLES)
+        .map(|_| {
+            for slot in resample.iter_mut() {
+                *slot = samples[rng.below(samples.len())];
+            }
+            self::mean(&resample)
+        })
+        .collect();
+    means.sort_by(f64::total_cmp);
+
+    Some(Summary {
+        min: sorted[0],
+        max: sorted[sorted.len() - 1],
+        mean,
+        median: percentile(&sorted, 0.5),
+        stddev,
+        p95: percentile(&sorted, 0.05),
+        p99: percentile(&sorted, 0.01),
+        ci_low: percentile(&means, 0.025),
+        ci_high: percentile(&means, 0.975),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_samples() {
+        assert!(summarize(&[]).is_none());
+        assert!(relative_stderr(&[]).is_none());
+    }
+
+    #[test]
+    fn single_sample() {
+        let summary = summarize(&[250.0]).unwrap();
+        for value in [
+            summary.min,
+            summary.max,
+            summary.mean,
+            summary.median,
+            summary.p95,
+            summary.p99,
+            summary.ci_low,
+            summary.ci_high,
+        ] {
+            assert_eq!(value, 250.0);
+        }
+        assert_eq!(summary.stddev, 0.0);
+        assert!(relative_stderr(&[250.0]).is_none());
+    }
+
+    #[test]
+    fn several_samples() {
+        let summary = summarize(&[4.0, 1.0, 3.0, 2.0]).unwrap();
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 4.0);
+        assert_eq!(summary.mean, 2.5);
+        assert_eq!(summary.median, 2.5);
+        // Sample standard deviation: sqrt(5 / 3)
+        assert!((summary.stddev - (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+        // The slow tail, interpolated between the two lowest samples
+        assert!((summary.p95 - 1.15).abs() < 1e-12);
+        assert!((summary.p99 - 1.03).abs() < 1e-12);
+        assert!(summary.ci_low <= summary.mean && summary.mean <= summary.ci_high);
+        assert!(summary.ci_low >= 1.0 && summary.ci_high <= 4.0);
+    }
+
+    #[test]
+    fn identical_samples_have_no_spread() {
+        let summary = summarize(&[7.0; 5]).unwrap();
+        assert_eq!(summary.stddev, 0.0);
+        assert_eq!((summary.ci_low, summary.ci_high), (7.0, 7.0));
+        assert_eq!(relative_stderr(&[7.0; 5]), Some(0.0));
+    }
+
+    #[test]
+    fn relative_stderr_of_two_samples() {
+        // stddev sqrt(2), over sqrt(2) samples, over a mean of 10
+        let stderr = relative_stderr(&[9.0, 11.0]).unwrap();
+        assert!((stderr - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bootstrap_is_reproducible() {
+        let samples = [10.0, 12.0, 9.5, 11.0, 30.0];
+        let (a, b) = (summarize(&samples).unwrap(), summarize(&samples).unwrap());
+        assert_eq!((a.ci_low, a.ci_high), (b.ci_low, b.ci_high));
+    }
+
+    #[test]
+    fn stat_names_round_trip() {
+        for name in ["total", "min", "max", "mean", "median", "p95", "p99"] {
+            assert_eq!(Stat::parse(name).unwrap().label(), name);
+        }
+        assert!(Stat::parse("average").is_none());
+        assert!(Stat::parse("").is_none());
+    }
+}
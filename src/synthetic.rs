@@ -0,0 +1,464 @@
+//! Generated inputs for `--synthetic`, to see how codecs behave on edge cases
+//! without hunting for a file that has them.
+//!
+//! The data is produced in fixed-size blocks, each from its own SplitMix64
+//! stream derived from the seed and the block's index, so any offset can be
+//! read (and re-read) without generating what comes before it.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::rng::SplitMix64;
+
+/// Seed used unless `--seed` gives another.
+pub const DEFAULT_SEED: u64 = 0x5eed;
+
+/// Bytes generated at a time; also the size of a `mixed` region.
+const BLOCK_SIZE: usize = 64 * 1024;
+const DEFAULT_PERIOD: usize = 4096;
+const DEFAULT_ENTROPY: f64 = 4.0;
+const DEFAULT_COMPRESSIBLE: f64 = 0.5;
+
+/// Words in the `text` vocabulary, and successors each word can have.
+const VOCABULARY: usize = 2048;
+const SUCCESSORS: usize = 16;
+/// Lowercase letters from most to least common in English text.
+const LETTERS: &[u8] = b"etaoinshrdlcumwfgypbvkjxqz";
+
+#[derive(Clone, Copy)]
+pub enum Kind {
+    /// All zero bytes.
+    Zeros,
+    /// Uniformly random, i.e. incompressible, bytes.
+    Random,
+    /// A random pattern of `period` bytes repeated over and over.
+    Pattern { period: usize },
+    /// English-looking words from a word-level Markov chain.
+    Text,
+    /// Independent bytes from a skewed distribution with `bits` of Shannon
+    /// entropy per byte.
+    Entropy { bits: f64 },
+    /// Regions of `text` and of random bytes, a `compressible` share of them
+    /// text.
+    Mixed { compressible: f64 },
+}
+
+/// A parsed `--synthetic` argument.
+#[derive(Clone, Copy)]
+pub struct Spec {
+    pub kind: Kind,
+    pub size: usize,
+}
+
+impl Spec {
+    /// Parses `KIND[=PARAM]:SIZE`, e.g. `zeros:64M`, `pattern=1K:64M`,
+    /// `entropy=2.5:1G` or `mixed=0.25:256M`.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (kind, size) = s.rsplit_once(':').ok_or_else(|| {
+            format!(
+                "--synthetic '{}' must look like KIND:SIZE, e.g. text:64M",
+                s
+            )
+        })?;
+        let size = crate::cli::parse_size(size)
+            .filter(|&size| size > 0)
+            .ok_or_else(|| format!("--synthetic size '{}' must be a positive size", size))?;
+        let (name, param) = match kind.split_once('=') {
+            Some((name, param)) => (name, Some(param)),
+            None => (kind, None),
+        };
+        let number = |range: std::ops::RangeInclusive<f64>, default: f64| match param {
+            None => Ok(default),
+            Some(param) => param
+                .parse::<f64>()
+                .ok()
+                .filter(|n| range.contains(n))
+                .ok_or_else(|| {
+                    format!(
+                        "--synthetic {} parameter must be a number in {}..{}",
+                        name,
+                        range.start(),
+                        range.end()
+                    )
+                }),
+        };
+        let no_param = |kind: Kind| match param {
+            None => Ok(kind),
+            Some(_) => Err(format!("--synthetic {} takes no parameter", name)),
+        };
+        let kind = match name {
+            "zeros" => no_param(Kind::Zeros)?,
+            "random" => no_param(Kind::Random)?,
+            "text" => no_param(Kind::Text)?,
+            "pattern" => Kind::Pattern {
+                period: match param {
+                    None => DEFAULT_PERIOD,
+                    Some(param) => crate::cli::parse_size(param)
+                        .filter(|&period| period > 0)
+                        .ok_or_else(|| {
+                            "--synthetic pattern period must be a positive size".to_string()
+                        })?,
+                },
+            },
+            "entropy" => Kind::Entropy {
+                bits: number(0.0..=8.0, DEFAULT_ENTROPY)?,
+            },
+            "mixed" => Kind::Mixed {
+                compressible: number(0.0..=1.0, DEFAULT_COMPRESSIBLE)?,
+            },
+            _ => {
+                return Err(format!(
+                    "--synthetic: unknown kind '{}' (zeros, random, pattern, text, entropy \
+                     or mixed)",
+                    name
+                ))
+            }
+        };
+        Ok(Self { kind, size })
+    }
+
+    /// How the input is named in reports, e.g. `synthetic entropy=2.5 (seed 1)`.
+    pub fn label(&self, seed: u64) -> String {
+        let kind = match self.kind {
+            Kind::Zeros => "zeros".to_string(),
+            Kind::Random => "random".to_string(),
+            Kind::Pattern { period } => format!("pattern={}", period),
+            Kind::Text => "text".to_string(),
+            Kind::Entropy { bits } => format!("entropy={}", bits),
+            Kind::Mixed { compressible } => format!("mixed={}", compressible),
+        };
+        format!("synthetic {} (seed {})", kind, seed)
+    }
+}
+
+/// The `text` generator's vocabulary and the successors of each word.
+struct Markov {
+    words: Vec<Vec<u8>>,
+    successors: Vec<[u16; SUCCESSORS]>,
+}
+
+impl Markov {
+    fn new(rng: &mut SplitMix64) -> Self {
+        let words = (0..VOCABULARY)
+            .map(|_| {
+                let len = 1 + rng.below(4) + rng.below(6);
+                // The lower of two draws favours the common letters
+                (0..len)
+                    .map(|_| LETTERS[rng.below(LETTERS.len()).min(rng.below(LETTERS.len()))])
+                    .collect()
+            })
+            .collect();
+        let successors = (0..VOCABULARY)
+            .map(|_| std::array::from_fn(|_| rng.below(VOCABULARY) as u16))
+            .collect();
+        Self { words, successors }
+    }
+
+    /// Fills `buf` with sentences, starting from a word picked by `rng`.
+    fn fill(&self, rng: &mut SplitMix64, buf: &mut [u8]) {
+        let mut word = rng.below(VOCABULARY);
+        let mut sentence_start = true;
+        let mut offset = 0;
+        while offset < buf.len() {
+            let mut text = self.words[word].clone();
+            if sentence_start {
+                text[0] = text[0].to_ascii_uppercase();
+            }
+            sentence_start = rng.below(12) == 0;
+            if sentence_start {
+                text.extend_from_slice(if rng.below(8) == 0 { b".\n" } else { b". " });
+            } else {
+                text.push(b' ');
+            }
+            let len = text.len().min(buf.len() - offset);
+            buf[offset..offset + len].copy_from_slice(&text[..len]);
+            offset += len;
+            // Early successors are likelier, as in natural text
+            word = self.successors[word][rng.below(SUCCESSORS).min(rng.below(SUCCESSORS))] as usize;
+        }
+    }
+}
+
+/// Cumulative thresholds, over the full `u64` range, of a distribution on
+/// byte values with `bits` of entropy: `p(k)` proportional to `r^k`, with `r`
+/// found by bisection since the entropy grows with it.
+fn entropy_thresholds(bits: f64) -> Vec<u64> {
+    let distribution = |r: f64| -> Vec<f64> {
+        let weights: Vec<f64> = (0..256).map(|k| r.powi(k)).collect();
+        let total: f64 = weights.iter().sum();
+        weights.iter().map(|w| w / total).collect()
+    };
+    let entropy =
+        |p: &[f64]| -> f64 { p.iter().filter(|&&p| p > 0.0).map(|p| -p * p.log2()).sum() };
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..100 {
+        let mid = (low + high) / 2.0;
+        if entropy(&distribution(mid)) < bits {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    let mut cumulative = 0.0;
+    distribution(high)
+        .iter()
+        .map(|p| {
+            cumulative += p;
+            (cumulative.min(1.0) * u64::MAX as f64) as u64
+        })
+        .collect()
+}
+
+/// A `--synthetic` input, readable and seekable like the file it stands in
+/// for.
+pub struct Synthetic {
+    kind: Kind,
+    size: usize,
+    seed: u64,
+    position: usize,
+    /// The block holding `position`, once generated.
+    block: Option<(usize, Vec<u8>)>,
+    pattern: Vec<u8>,
+    markov: Option<Markov>,
+    thresholds: Vec<u64>,
+}
+
+impl Synthetic {
+    pub fn new(spec: &Spec, seed: u64) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let pattern = match spec.kind {
+            Kind::Pattern { period } => (0..period).map(|_| rng.next_u64() as u8).collect(),
+            _ => Vec::new(),
+        };
+        let markov =
+            matches!(spec.kind, Kind::Text | Kind::Mixed { .. }).then(|| Markov::new(&mut rng));
+        let thresholds = match spec.kind {
+            Kind::Entropy { bits } => entropy_thresholds(bits),
+            _ => Vec::new(),
+        };
+        Self {
+            kind: spec.kind,
+            size: spec.size,
+            seed,
+            position: 0,
+            block: None,
+            pattern,
+            markov,
+            thresholds,
+        }
+    }
+
+    /// Generates block `index` from its own random stream.
+    fn generate(&self, index: usize, buf: &mut [u8]) {
+        let mut rng = SplitMix64::new(
+            SplitMix64::new(self.seed ^ (index as u64 + 1).wrapping_mul(0xd1b5_4a32_d192_ed03))
+                .next_u64(),
+        );
+        let random = |rng: &mut SplitMix64, buf: &mut [u8]| {
+            for word in buf.chunks_mut(8) {
+                word.copy_from_slice(&rng.next_u64().to_le_bytes()[..word.len()]);
+            }
+        };
+        match self.kind {
+            Kind::Zeros => buf.fill(0),
+            Kind::Random => random(&mut rng, buf),
+            Kind::Pattern { period } => {
+                for (offset, byte) in buf.iter_mut().enumerate() {
+                    *byte = self.pattern[(index * BLOCK_SIZE + offset) % period];
+                }
+            }
+            Kind::Text => self.markov.as_ref().unwrap().fill(&mut rng, buf),
+            Kind::Entropy { .. } => {
+                for byte in buf.iter_mut() {
+                    let draw = rng.next_u64();
+                    *byte = self.thresholds.partition_point(|&t| t < draw).min(255) as u8;
+                }
+            }
+            Kind::Mixed { compressible } => {
+                if (rng.next_u64() as f64 / u64::MAX as f64) < compressible {
+                    self.markov.as_ref().unwrap().fill(&mut rng, buf);
+                } else {
+                    random(&mut rng, buf);
+                }
+            }
+        }
+    }
+}
+
+impl Read for Synthetic {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let index = self.position / BLOCK_SIZE;
+        if self.position >= self.size || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.block.as_ref().map(|(i, _)| *i) != Some(index) {
+            let len = BLOCK_SIZE.min(self.size - index * BLOCK_SIZE);
+            let mut block = vec![0u8; len];
+            self.generate(index, &mut block);
+            self.block = Some((index, block));
+        }
+        let block = &self.block.as_ref().unwrap().1;
+        let offset = self.position - index * BLOCK_SIZE;
+        let len = buf.len().min(block.len() - offset);
+        buf[..len].copy_from_slice(&block[offset..offset + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+impl Seek for Synthetic {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset as i64),
+            SeekFrom::Current(offset) => (self.position as i64).checked_add(offset),
+            SeekFrom::End(offset) => (self.size as i64).checked_add(offset),
+        };
+        match position {
+            Some(position) if position >= 0 => {
+                self.position = position as usize;
+                Ok(position as u64)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the synthetic input",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_and_parameters() {
+        assert!(matches!(Spec::parse("zeros:1K").unwrap().kind, Kind::Zeros));
+        assert!(matches!(
+            Spec::parse("random:1K").unwrap().kind,
+            Kind::Random
+        ));
+        assert!(matches!(Spec::parse("text:1K").unwrap().kind, Kind::Text));
+        assert!(matches!(
+            Spec::parse("pattern:1K").unwrap().kind,
+            Kind::Pattern {
+                period: DEFAULT_PERIOD
+            }
+        ));
+        assert!(matches!(
+            Spec::parse("pattern=1K:1M").unwrap().kind,
+            Kind::Pattern { period: 1024 }
+        ));
+        assert!(matches!(
+            Spec::parse("entropy=2.5:1K").unwrap().kind,
+            Kind::Entropy { bits } if bits == 2.5
+        ));
+        assert!(matches!(
+            Spec::parse("entropy:1K").unwrap().kind,
+            Kind::Entropy { bits } if bits == DEFAULT_ENTROPY
+        ));
+        assert!(matches!(
+            Spec::parse("mixed=0:1K").unwrap().kind,
+            Kind::Mixed { compressible } if compressible == 0.0
+        ));
+        assert!(matches!(
+            Spec::parse("mixed=1:1K").unwrap().kind,
+            Kind::Mixed { compressible } if compressible == 1.0
+        ));
+    }
+
+    #[test]
+    fn suffixed_sizes() {
+        for (size, bytes) in [
+            ("1", 1),
+            ("100B", 100),
+            ("64K", 64 << 10),
+            ("64kib", 64 << 10),
+            ("3M", 3 << 20),
+            ("3MB", 3 << 20),
+            ("2G", 2 << 30),
+        ] {
+            assert_eq!(
+                Spec::parse(&format!("text:{}", size)).unwrap().size,
+                bytes,
+                "{}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_zero_and_malformed_sizes() {
+        for size in ["0", "0K", "", "K", "-1", "1.5M", "1T", "64 M x"] {
+            assert_eq!(
+                Spec::parse(&format!("text:{}", size)).err().unwrap(),
+                format!("--synthetic size '{}' must be a positive size", size)
+            );
+        }
+        assert!(Spec::parse("pattern=0:1M").is_err());
+        assert!(Spec::parse("pattern=x:1M").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_specs() {
+        assert!(Spec::parse("text")
+            .err()
+            .unwrap()
+            .contains("must look like KIND:SIZE"));
+        assert!(Spec::parse("")
+            .err()
+            .unwrap()
+            .contains("must look like KIND:SIZE"));
+        assert!(Spec::parse("noise:1M")
+            .err()
+            .unwrap()
+            .contains("unknown kind 'noise'"));
+        assert!(Spec::parse(":1M")
+            .err()
+            .unwrap()
+            .contains("unknown kind ''"));
+        assert_eq!(
+            Spec::parse("zeros=1:1M").err().unwrap(),
+            "--synthetic zeros takes no parameter"
+        );
+        for spec in [
+            "entropy=9:1M",
+            "entropy=-1:1M",
+            "entropy=nan:1M",
+            "mixed=1.5:1M",
+        ] {
+            assert!(Spec::parse(spec)
+                .err()
+                .unwrap()
+                .contains("parameter must be a number"));
+        }
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(
+            Spec::parse("entropy=2.5:1M").unwrap().label(1),
+            "synthetic entropy=2.5 (seed 1)"
+        );
+        assert_eq!(
+            Spec::parse("pattern=1K:1M").unwrap().label(DEFAULT_SEED),
+            "synthetic pattern=1024 (seed 24301)"
+        );
+    }
+
+    #[test]
+    fn same_seed_same_data_from_any_offset() {
+        let spec = Spec::parse("mixed:200K").unwrap();
+        let mut whole = Vec::new();
+        Synthetic::new(&spec, 7).read_to_end(&mut whole).unwrap();
+        assert_eq!(whole.len(), 200 * 1024);
+
+        let mut again = Synthetic::new(&spec, 7);
+        again.seek(SeekFrom::Start(100_000)).unwrap();
+        let mut tail = Vec::new();
+        again.read_to_end(&mut tail).unwrap();
+        assert_eq!(tail, whole[100_000..]);
+
+        let mut other = Vec::new();
+        Synthetic::new(&spec, 8).read_to_end(&mut other).unwrap();
+        assert_ne!(other, whole);
+    }
+}